@@ -51,7 +51,12 @@ const main = async () => {
     // Get the code ID from the receipt
     const { codeId } = uploadReceipt;
 
-    const initMsg = { max_size: MAX_SIZE, prng_seed: "seed" };
+    // the deploying key also acts as the scoring oracle, see setData.ts
+    const initMsg = {
+      max_size: MAX_SIZE,
+      prng_seed: "seed",
+      oracles: [accAddress],
+    };
     const contract = await client
       .instantiate(codeId, initMsg, accAddress.slice(6))
       .catch((err) => {
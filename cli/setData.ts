@@ -18,7 +18,16 @@ const KEYPAIR = require("./keys.json");
 const CONTRACT_DATA = require("./contract.json");
 const WASM = fs.readFileSync("./contract.wasm");
 
+// Scores can only be recorded by an oracle, so this signs with the oracle key from keys.json
+// (registered as the oracle by index.ts) and records the score of the borrower passed as the
+// first argument, e.g. `yarn set_data secret1...`.
+const BORROWER = process.argv[2];
+
 const main = async () => {
+  if (!BORROWER) {
+    log(chalk.red.bold("Usage: yarn set_data <borrower address>"));
+    return;
+  }
   if (KEYPAIR?.mnemonic) {
     const signingPen = await Secp256k1Pen.fromMnemonic(KEYPAIR.mnemonic);
     const pubkey = encodeSecp256k1Pubkey(signingPen.pubkey);
@@ -35,19 +44,20 @@ const main = async () => {
 
     const submitScoreMsg = {
       record: {
-        score: 400,
+        subject: BORROWER,
+        score: 581,
+        band: "FAIR",
         description:
           "Your SCRTSibyl score is FAIR, with a total of 581 points, which qualifies you for a loan of up to $5000 USD. SCRTSibyl computed your score accounting for your Plaid diamond 12.5% apr interest credit card credit card your total current balance of $44520 and your 9 different bank accounts. An error occurred during computation of the metrics: velocity, and your score was rounded down. Try again later or log in using a different account.",
       },
     };
-    let handle_response = await client.execute(
-      CONTRACT_DATA.contractAddress,
-      submitScoreMsg
-    );
-
-    const strRes = Buffer.from(handle_response.data.buffer).toString();
-    if (strRes.includes("Score recorded")) {
+    try {
+      await client.execute(CONTRACT_DATA.contractAddress, submitScoreMsg);
       log(chalk.green.bold("Score Submission Successful!"));
+    } catch (err) {
+      // Contract errors carry a stable code, e.g. "Generic error: description_too_long: ..."
+      const code = /Generic error: ([a-z_]+): /.exec(err.message)?.[1];
+      log(chalk.red.bold("Score Submission Failed:", code ?? err.message));
     }
   }
 };
@@ -3,7 +3,7 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
-use scrtsibyl_contract::msg::{HandleMsg, InitMsg, QueryMsg, ScoreResponse};
+use scrtsibyl_contract::msg::{HandleMsg, InitMsg, QueryMsg, ReceiveMsg, ScoreResponse};
 use scrtsibyl_contract::state::State;
 
 fn main() {
@@ -15,6 +15,7 @@ fn main() {
     export_schema(&schema_for!(InitMsg), &out_dir);
     export_schema(&schema_for!(HandleMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
+    export_schema(&schema_for!(ReceiveMsg), &out_dir);
     export_schema(&schema_for!(State), &out_dir);
     export_schema(&schema_for!(ScoreResponse), &out_dir);
 }
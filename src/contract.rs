@@ -1,51 +1,149 @@
+use crate::error::{ContractError, ContractResult};
+use crate::interface::ScoreCallbackMsg;
 use crate::msg::{
-    HandleAnswer, HandleMsg, InitMsg, QueryMsg, QueryWithPermit, ResponseStatus, ScoreResponse,
-    StateResponse, StatsResponse,
+    AccessLogEntryInfo, AccessLogResponse, AttestationPayload, BandCount, BandResponse,
+    ContractStatus, EligibilityResponse, GrantInfo, GrantsResponse, HandleAnswer, HandleMsg,
+    HistoryResponse, InitMsg, ListScoresResponse, PendingRequestsResponse, QueryMsg,
+    QueryWithPermit, ReadFee, ReceiveMsg, ResponseStatus, ScoreAttestation, ScoreBand,
+    ScoreComponent, ScoreField, ScoreHistoryEntry, ScoreMetadata, ScoreRequestInfo, ScoreResponse,
+    StatsResponse, ViewingKeysResponse, SCORE_BANDS, SCORE_FIELDS,
 };
 use crate::state::{
-    does_user_exist, load, may_load, read_viewing_key, save, write_viewing_key, Config, Constants,
-    ReadonlyConfig, State, User, CONFIG_KEY,
+    accepts_paid_reads, append_access_log, append_score_history, clear_access_log,
+    clear_score_history, get_access_log, get_score_history, get_user_index, is_oracle, list_grants,
+    load_block_time, load_state, may_load_counted_user, may_load_legacy_state, may_load_user,
+    migrate_user, read_callback_contract, read_grant, read_oracle_key, read_pending_callbacks,
+    read_score_requests, read_subscribers, read_viewing_key, read_viewing_key_names,
+    read_viewing_keys, remove_callback_contract, remove_grant, remove_oracle, remove_oracle_key,
+    remove_user, remove_viewing_key, save_block, save_state, save_user, use_nonce, user_count,
+    write_accepts_paid_reads, write_callback_contract, write_grant, write_named_viewing_key,
+    write_oracle, write_oracle_key, write_pending_callbacks, write_score_requests,
+    write_subscribers, write_viewing_key, Config, Constants, ContractVersion, Grant,
+    ReadonlyConfig, ScoreEntry, ScoreRequest, State, User,
 };
 use crate::viewing_key::{ViewingKey, VIEWING_KEY_SIZE};
 use cosmwasm_std::{
-    to_binary, Api, Binary, Env, Extern, HandleResponse, HumanAddr, InitResponse, Querier,
-    QueryResult, StdError, StdResult, Storage,
+    from_binary, to_binary, Api, BankMsg, Binary, CanonicalAddr, Coin, CosmosMsg, Env, Extern,
+    HandleResponse, HumanAddr, InitResponse, Querier, QueryResult, StdError, StdResult, Storage,
+    Uint128,
 };
 use ripemd160::Digest;
+use secret_toolkit::crypto::secp256k1::{PublicKey, Signature};
 use secret_toolkit::permit::{validate, Permission, Permit, RevokedPermits};
+use secret_toolkit::snip20::{register_receive_msg, transfer_msg};
+use secret_toolkit::utils::HandleCallback;
 use sha2::Sha256;
 
 pub const PREFIX_REVOKED_PERMITS: &str = "revoked_permits";
+/// Permits whose name starts with this only answer `VerifyEligibility`, and only those do.
+pub const ELIGIBILITY_PERMIT_PREFIX: &str = "eligibility";
 pub const SHA256_HASH_SIZE: usize = 32;
+pub const CONTRACT_NAME: &str = env!("CARGO_PKG_NAME");
+pub const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Scores older than this many seconds (180 days) are reported as stale, unless the
+/// validity window is configured otherwise.
+pub const DEFAULT_SCORE_VALIDITY: u64 = 180 * 24 * 60 * 60;
+/// Messages to token contracts are padded to a multiple of this many bytes.
+pub const BLOCK_SIZE: usize = 256;
+/// Every query checks the given key against all keys of the address, so their number is capped.
+pub const MAX_NAMED_VIEWING_KEYS: usize = 10;
+/// Every recorded score sends a callback to each subscriber, so their number is capped.
+pub const MAX_SUBSCRIBERS: usize = 10;
+/// Aggregate statistics are only disclosed for groups of at least this many users, unless
+/// configured otherwise.
+pub const DEFAULT_K_ANONYMITY: u64 = 5;
+/// The mean score is rounded to a multiple of this, so comparing it before and after a
+/// record doesn't give away the recorded score.
+pub const MEAN_SCORE_PRECISION: u64 = 10;
+/// Limits on the score requests lenders can send a borrower. Beyond `MAX_PENDING_REQUESTS`,
+/// new requests push out the oldest ones.
+pub const MAX_PURPOSE_SIZE: usize = 280;
+pub const MAX_PENDING_REQUESTS: usize = 20;
+/// Requests expire after at most this many seconds (30 days), so stale ones don't pile up.
+pub const MAX_REQUEST_LIFETIME: u64 = 30 * 24 * 60 * 60;
+/// Page sizes of `ListScores`
+pub const DEFAULT_LIST_LIMIT: u32 = 30;
+pub const MAX_LIST_LIMIT: u32 = 100;
+/// The range of scores the SCRTSibyl oracle emits
+pub const DEFAULT_MIN_SCORE: u64 = 300;
+pub const DEFAULT_MAX_SCORE: u64 = 900;
 
 pub fn init<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
     msg: InitMsg,
 ) -> StdResult<InitResponse> {
-    let max_size = match valid_max_size(msg.max_size) {
-        Some(v) => v,
-        None => {
-            return Err(StdError::generic_err(
-                "Invalid max_size. Must be in the range of 1..65535.",
-            ))
-        }
+    let max_size = valid_max_size(msg.max_size).ok_or(ContractError::InvalidMaxSize)?;
+    let min_score = msg.min_score.unwrap_or(DEFAULT_MIN_SCORE);
+    let max_score = msg.max_score.unwrap_or(DEFAULT_MAX_SCORE);
+    check_score_range(min_score, max_score)?;
+    let messages = match &msg.read_fee {
+        Some(read_fee) => vec![register_fee_token(read_fee, &env)?],
+        None => vec![],
     };
 
     let state = State {
+        status: ContractStatus::Normal,
+        admin: msg.admin.unwrap_or_else(|| env.message.sender.clone()),
+        pending_admin: None,
         max_size,
-        score_count: 0_u64,
+        score_validity: msg.score_validity.unwrap_or(DEFAULT_SCORE_VALIDITY),
+        min_score,
+        max_score,
+        k_anonymity: msg.k_anonymity.unwrap_or(DEFAULT_K_ANONYMITY),
+        read_fee: msg.read_fee,
+        record_fee: msg.record_fee,
         prng_seed: sha_256(base64::encode(msg.prng_seed).as_bytes()).to_vec(),
     };
 
-    save(&mut deps.storage, CONFIG_KEY, &state)?;
+    save_state(&mut deps.storage, &state)?;
+
+    for oracle in msg.oracles.unwrap_or_default() {
+        let oracle = deps.api.canonical_address(&oracle)?;
+        write_oracle(&mut deps.storage, &oracle);
+    }
 
     let mut config = Config::from_storage(&mut deps.storage);
     config.set_constants(&Constants {
         contract_address: env.contract.address,
     })?;
+    config.set_contract_version(&ContractVersion {
+        name: CONTRACT_NAME.to_string(),
+        version: CONTRACT_VERSION.to_string(),
+    })?;
+
+    Ok(InitResponse {
+        messages,
+        log: vec![],
+    })
+}
+
+/// Checks the fee and asks its token to notify this contract of incoming payments.
+fn register_fee_token(read_fee: &ReadFee, env: &Env) -> ContractResult<CosmosMsg> {
+    if read_fee.borrower_share > 100 {
+        return Err(ContractError::InvalidFeeShare {
+            share: read_fee.borrower_share,
+        });
+    }
+
+    Ok(register_receive_msg(
+        env.contract_code_hash.clone(),
+        None,
+        BLOCK_SIZE,
+        read_fee.token.code_hash.clone(),
+        read_fee.token.address.clone(),
+    )?)
+}
+
+fn check_score_range(min_score: u64, max_score: u64) -> ContractResult<()> {
+    if min_score > max_score {
+        return Err(ContractError::InvalidScoreRange {
+            min_score,
+            max_score,
+        });
+    }
 
-    Ok(InitResponse::default())
+    Ok(())
 }
 
 // limit the max message size to values in 1..65535
@@ -62,29 +160,126 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
     env: Env,
     msg: HandleMsg,
 ) -> StdResult<HandleResponse> {
-    match msg {
-        HandleMsg::Record { score, description } => try_record(deps, env, score, description),
+    save_block(&mut deps.storage, &env.block)?;
+
+    match may_load_legacy_state(&deps.storage)? {
+        // a state that predates versioning has to be claimed before anything else can run
+        Some(_) if matches!(msg, HandleMsg::MigrateStorage { .. }) => {}
+        _ => check_status(load_state(&deps.storage)?.status, &msg)?,
+    }
+
+    let response = match msg {
+        HandleMsg::Record {
+            subject,
+            score,
+            band,
+            description,
+            components,
+            loan_ceiling,
+            attestation,
+        } => try_record(
+            deps,
+            env,
+            subject,
+            score,
+            band,
+            description,
+            components.unwrap_or_default(),
+            loan_ceiling,
+            attestation,
+        ),
+        HandleMsg::AddOracle { address, .. } => try_add_oracle(deps, env, address),
+        HandleMsg::AddOracleKey { oracle, pubkey, .. } => {
+            try_add_oracle_key(deps, env, oracle, pubkey)
+        }
+        HandleMsg::RemoveOracleKey { pubkey, .. } => try_remove_oracle_key(deps, env, pubkey),
+        HandleMsg::RemoveOracle { address, .. } => try_remove_oracle(deps, env, address),
+        HandleMsg::AddCallbackContract {
+            contract,
+            code_hash,
+            ..
+        } => try_add_callback_contract(deps, env, contract, code_hash),
+        HandleMsg::RemoveCallbackContract { contract, .. } => {
+            try_remove_callback_contract(deps, env, contract)
+        }
+        HandleMsg::SetMaxSize { max_size, .. } => try_set_max_size(deps, env, max_size),
+        HandleMsg::SetScoreValidity { score_validity, .. } => {
+            try_set_score_validity(deps, env, score_validity)
+        }
+        HandleMsg::SetContractStatus { level, .. } => try_set_contract_status(deps, env, level),
+        HandleMsg::SetKAnonymity { k_anonymity, .. } => try_set_k_anonymity(deps, env, k_anonymity),
+        HandleMsg::SetReadFee { read_fee, .. } => try_set_read_fee(deps, env, read_fee),
+        HandleMsg::SetRecordFee { record_fee, .. } => try_set_record_fee(deps, env, record_fee),
+        HandleMsg::Withdraw {
+            amount, recipient, ..
+        } => try_withdraw(deps, env, amount, recipient),
+        HandleMsg::SetScoreRange {
+            min_score,
+            max_score,
+            ..
+        } => try_set_score_range(deps, env, min_score, max_score),
+        HandleMsg::MigrateStorage {
+            addresses,
+            legacy_prng_seed,
+            ..
+        } => try_migrate_storage(deps, env, addresses, legacy_prng_seed),
+        HandleMsg::ChangeAdmin { address, .. } => try_change_admin(deps, env, address),
+        HandleMsg::AcceptAdmin { .. } => try_accept_admin(deps, env),
+        HandleMsg::GrantAccess {
+            grantee,
+            expires_at,
+            max_reads,
+            ..
+        } => try_grant_access(deps, env, grantee, expires_at, max_reads),
+        HandleMsg::RevokeAccess { grantee, .. } => try_revoke_access(deps, env, grantee),
+        HandleMsg::RequestScore {
+            borrower,
+            purpose,
+            expires_at,
+            ..
+        } => try_request_score(deps, env, borrower, purpose, expires_at),
+        HandleMsg::ApproveRequest { lender, .. } => try_answer_request(deps, env, lender, true),
+        HandleMsg::DenyRequest { lender, .. } => try_answer_request(deps, env, lender, false),
+        HandleMsg::Subscribe { contract, .. } => try_subscribe(deps, env, contract),
+        HandleMsg::Unsubscribe { contract, .. } => try_unsubscribe(deps, env, contract),
+        HandleMsg::DeliverCallback {
+            borrower, contract, ..
+        } => try_deliver_callback(deps, borrower, contract),
+        HandleMsg::ReadAsGrantee { owner, .. } => try_read_as_grantee(deps, env, owner),
+        HandleMsg::SetPaidReads { accept, .. } => try_set_paid_reads(deps, env, accept),
+        HandleMsg::Receive {
+            from, amount, msg, ..
+        } => try_receive(deps, env, from, amount, msg),
+        HandleMsg::ReadScoreLogged { owner, fields, .. } => {
+            try_read_score_logged(deps, env, owner, fields)
+        }
         HandleMsg::RevokePermit { permit_name, .. } => revoke_permit(deps, env, permit_name),
-        HandleMsg::WithPermit { permit, query } => permit_handle(deps, permit, query),
-        HandleMsg::GenerateViewingKey { entropy, .. } => {
-            try_generate_viewing_key(deps, env, entropy)
+        HandleMsg::WithPermit { permit, query } => permit_handle(deps, env, permit, query),
+        HandleMsg::GenerateViewingKey { entropy, name, .. } => {
+            try_generate_viewing_key(deps, env, entropy, name)
         }
-    }
+        HandleMsg::SetViewingKey { key, name, .. } => try_set_viewing_key(deps, env, key, name),
+        HandleMsg::DeleteViewingKey { name, .. } => try_delete_viewing_key(deps, env, name),
+        HandleMsg::DeleteMyScore { .. } => try_delete_my_score(deps, env),
+    };
+
+    Ok(response?)
 }
 
 pub fn try_generate_viewing_key<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
     entropy: String,
-) -> StdResult<HandleResponse> {
-    let config: State = load(&deps.storage, CONFIG_KEY)?;
+    name: Option<String>,
+) -> ContractResult<HandleResponse> {
+    let config: State = load_state(&deps.storage)?;
     let prng_seed = config.prng_seed;
 
-    let key = ViewingKey::new(&env, &prng_seed, (&entropy).as_ref());
+    let key = ViewingKey::new(&env, &prng_seed, entropy.as_ref());
 
     let message_sender = deps.api.canonical_address(&env.message.sender)?;
 
-    write_viewing_key(&mut deps.storage, &message_sender, &key);
+    store_viewing_key(&mut deps.storage, &message_sender, name, &key)?;
 
     Ok(HandleResponse {
         messages: vec![],
@@ -93,36 +288,220 @@ pub fn try_generate_viewing_key<S: Storage, A: Api, Q: Querier>(
     })
 }
 
-fn permit_handle<S: Storage, A: Api, Q: Querier>(
+pub fn try_set_viewing_key<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    key: String,
+    name: Option<String>,
+) -> ContractResult<HandleResponse> {
+    let message_sender = deps.api.canonical_address(&env.message.sender)?;
+
+    store_viewing_key(&mut deps.storage, &message_sender, name, &ViewingKey(key))?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::SetViewingKey {
+            status: ResponseStatus::Success,
+        })?),
+    })
+}
+
+pub fn try_delete_viewing_key<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    name: Option<String>,
+) -> ContractResult<HandleResponse> {
+    let message_sender = deps.api.canonical_address(&env.message.sender)?;
+
+    remove_viewing_key(&mut deps.storage, &message_sender, name.as_deref())?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::DeleteViewingKey {
+            status: ResponseStatus::Success,
+        })?),
+    })
+}
+
+pub fn try_delete_my_score<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> ContractResult<HandleResponse> {
+    let owner = deps.api.canonical_address(&env.message.sender)?;
+
+    let previous = may_load_counted_user(&deps.storage, &owner)?;
+    let deleted = previous.is_some();
+    if let Some((previous, counted)) = previous {
+        remove_user(&mut deps.storage, &owner)?;
+
+        if counted {
+            let mut config = Config::from_storage(&mut deps.storage);
+            let mut distribution = config.distribution()?;
+            distribution.remove(&previous);
+            config.set_distribution(&distribution)?;
+        }
+    }
+    clear_score_history(&mut deps.storage, &owner)?;
+    clear_access_log(&mut deps.storage, &owner)?;
+
+    for (grantee, _) in list_grants(&deps.storage, &owner)? {
+        remove_grant(&mut deps.storage, &owner, &grantee)?;
+    }
+
+    write_score_requests(&mut deps.storage, &owner, &[])?;
+    write_subscribers(&mut deps.storage, &owner, &[])?;
+    write_pending_callbacks(&mut deps.storage, &owner, &[])?;
+    write_accepts_paid_reads(&mut deps.storage, &owner, false);
+
+    remove_viewing_key(&mut deps.storage, &owner, None)?;
+    for name in read_viewing_key_names(&deps.storage, &owner)? {
+        remove_viewing_key(&mut deps.storage, &owner, Some(&name))?;
+    }
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::DeleteMyScore {
+            status: ResponseStatus::Success,
+            deleted,
+        })?),
+    })
+}
+
+fn store_viewing_key<S: Storage>(
+    storage: &mut S,
+    owner: &CanonicalAddr,
+    name: Option<String>,
+    key: &ViewingKey,
+) -> ContractResult<()> {
+    match name {
+        None => write_viewing_key(storage, owner, key),
+        Some(name) => {
+            let names = read_viewing_key_names(storage, owner)?;
+            if names.len() >= MAX_NAMED_VIEWING_KEYS && !names.contains(&name) {
+                return Err(ContractError::TooManyViewingKeys {
+                    max: MAX_NAMED_VIEWING_KEYS,
+                });
+            }
+            write_named_viewing_key(storage, owner, &name, key)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_permit<S: Storage, A: Api, Q: Querier>(
     deps: &Extern<S, A, Q>,
-    permit: Permit,
-    query: QueryWithPermit,
-) -> StdResult<HandleResponse> {
+    permit: &Permit,
+) -> ContractResult<HumanAddr> {
     // Validate permit content
     let token_address = ReadonlyConfig::from_storage(&deps.storage)
         .constants()?
         .contract_address;
 
-    let account = validate(deps, PREFIX_REVOKED_PERMITS, &permit, token_address)?;
+    validate(deps, PREFIX_REVOKED_PERMITS, permit, token_address).map_err(|err| {
+        let reason = match err {
+            StdError::GenericErr { msg, .. } => msg,
+            err => err.to_string(),
+        };
+        ContractError::PermitInvalid { reason }
+    })
+}
+
+/// Eligibility permits are handed to other contracts, so they must not read the score itself.
+fn check_permit_purpose(permit: &Permit, eligibility: bool) -> ContractResult<()> {
+    let is_eligibility_permit = permit
+        .params
+        .permit_name
+        .starts_with(ELIGIBILITY_PERMIT_PREFIX);
+    if is_eligibility_permit != eligibility {
+        let reason = if eligibility {
+            format!(
+                "VerifyEligibility needs a permit named {}...",
+                ELIGIBILITY_PERMIT_PREFIX
+            )
+        } else {
+            format!(
+                "permits named {}... only verify eligibility",
+                ELIGIBILITY_PERMIT_PREFIX
+            )
+        };
+        return Err(ContractError::PermitInvalid { reason });
+    }
+
+    Ok(())
+}
+
+fn check_permission(permit: &Permit, permission: Permission, action: &str) -> ContractResult<()> {
+    if !permit.check_permission(&permission) {
+        return Err(ContractError::PermissionDenied {
+            action: action.to_string(),
+            permissions: format!("{:?}", permit.params.permissions),
+        });
+    }
+
+    Ok(())
+}
+
+fn permit_handle<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    env: Env,
+    permit: Permit,
+    query: QueryWithPermit,
+) -> ContractResult<HandleResponse> {
+    check_permit_purpose(&permit, false)?;
+    let account = validate_permit(deps, &permit)?;
     // Permit validated! We can now execute the query.
 
-    match query {
+    let data = match query {
+        QueryWithPermit::Band {} => {
+            check_permission(&permit, Permission::Balance, "query score band")?;
+
+            to_binary(&HandleAnswer::PermitBand {
+                data: query_read(deps, &account, env.block.time)
+                    .map(band_only)
+                    .map_err(StdError::from),
+            })?
+        }
         QueryWithPermit::Balance {} => {
-            if !permit.check_permission(&Permission::Balance) {
-                return Err(StdError::generic_err(format!(
-                    "No permission to query score, got permissions {:?}",
-                    permit.params.permissions
-                )));
-            }
+            check_permission(&permit, Permission::History, "query score")?;
+
+            to_binary(&HandleAnswer::PermitHandle {
+                data: query_read(deps, &account, env.block.time)
+                    .map(without_breakdown)
+                    .map_err(StdError::from),
+            })?
         }
-    }
+        QueryWithPermit::Breakdown {} => {
+            check_permission(&permit, Permission::Owner, "query score breakdown")?;
+
+            to_binary(&HandleAnswer::PermitHandle {
+                data: query_read(deps, &account, env.block.time).map_err(StdError::from),
+            })?
+        }
+        QueryWithPermit::Grants {} => {
+            check_permission(&permit, Permission::Allowance, "query grants")?;
+
+            to_binary(&HandleAnswer::PermitGrants {
+                data: query_grants(deps, &account).map_err(StdError::from),
+            })?
+        }
+        QueryWithPermit::History { page, page_size } => {
+            check_permission(&permit, Permission::History, "query score history")?;
+
+            to_binary(&HandleAnswer::PermitHistory {
+                data: query_history(deps, &account, page.unwrap_or(0), page_size)
+                    .map_err(StdError::from),
+            })?
+        }
+    };
 
     Ok(HandleResponse {
         messages: vec![],
         log: vec![],
-        data: Some(to_binary(&HandleAnswer::PermitHandle {
-            data: query_read(deps, &account),
-        })?),
+        data: Some(data),
     })
 }
 
@@ -130,7 +509,7 @@ fn revoke_permit<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
     permit_name: String,
-) -> StdResult<HandleResponse> {
+) -> ContractResult<HandleResponse> {
     RevokedPermits::revoke_permit(
         &mut deps.storage,
         PREFIX_REVOKED_PERMITS,
@@ -147,331 +526,3873 @@ fn revoke_permit<S: Storage, A: Api, Q: Querier>(
     })
 }
 
-pub fn try_record<S: Storage, A: Api, Q: Querier>(
+fn try_grant_access<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
-    score: u64,
-    description: String,
-) -> StdResult<HandleResponse> {
-    let sender_address = deps.api.canonical_address(&env.message.sender)?;
-    let user_state = does_user_exist(&deps.storage, sender_address.as_slice());
-    let description = description.as_bytes();
-    // create the User struct containing score  and timestamp
-    let stored_score = User {
-        score,
-        timestamp: env.block.time,
-        description: description.to_vec(),
-    };
-
-    save(&mut deps.storage, sender_address.as_slice(), &stored_score)?;
-
-    if !user_state {
-        let state: StateResponse = query_state(deps).unwrap();
-
-        let new_state = State {
-            max_size: state.max_size,
-            score_count: state.score_count + 1,
-            prng_seed: state.prng_seed,
-        };
-
-        save(&mut deps.storage, CONFIG_KEY, &new_state)?;
-    }
+    grantee: HumanAddr,
+    expires_at: Option<u64>,
+    max_reads: Option<u64>,
+) -> ContractResult<HandleResponse> {
+    let owner = deps.api.canonical_address(&env.message.sender)?;
+    let grantee = deps.api.canonical_address(&grantee)?;
 
-    let status: String = String::from("Score recorded!");
+    let grant = Grant {
+        expires_at,
+        max_reads,
+        reads: 0,
+    };
+    write_grant(&mut deps.storage, &owner, &grantee, &grant)?;
 
     Ok(HandleResponse {
         messages: vec![],
         log: vec![],
-        data: Some(to_binary(&HandleAnswer::Record { status })?),
+        data: Some(to_binary(&HandleAnswer::GrantAccess {
+            status: ResponseStatus::Success,
+        })?),
     })
 }
 
-fn query_read<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-    address: &HumanAddr,
-) -> StdResult<ScoreResponse> {
-    let status: String;
-    let description: String;
-    let mut score: Option<u64> = None;
-    let mut timestamp: Option<u64> = None;
-    let sender_address = deps.api.canonical_address(address)?;
-    let result: Option<User> = may_load(&deps.storage, sender_address.as_slice())
-        .ok()
-        .unwrap();
+fn try_revoke_access<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    grantee: HumanAddr,
+) -> ContractResult<HandleResponse> {
+    let owner = deps.api.canonical_address(&env.message.sender)?;
+    let grantee = deps.api.canonical_address(&grantee)?;
 
-    match result {
-        Some(stored_score) => {
-            score = Some(stored_score.score);
-            timestamp = Some(stored_score.timestamp);
-            description = String::from_utf8(stored_score.description).unwrap();
-            status = String::from("Score found.");
-        }
-        None => {
-            status = String::from("Score not found.");
-            description = String::from("N/A");
-            return Ok(ScoreResponse {
-                status,
-                timestamp,
-                score,
-                description,
-            });
-        }
-    }
+    remove_grant(&mut deps.storage, &owner, &grantee)?;
 
-    Ok(ScoreResponse {
-        score,
-        timestamp,
-        status,
-        description,
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::RevokeAccess {
+            status: ResponseStatus::Success,
+        })?),
     })
 }
 
-fn query_stats<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> StdResult<StatsResponse> {
-    let config: State = load(&deps.storage, CONFIG_KEY)?;
-    Ok(StatsResponse {
-        score_count: config.score_count,
-        max_size: config.max_size,
-    })
-}
+fn try_set_paid_reads<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    accept: bool,
+) -> ContractResult<HandleResponse> {
+    let borrower = deps.api.canonical_address(&env.message.sender)?;
+    write_accepts_paid_reads(&mut deps.storage, &borrower, accept);
 
-fn query_state<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> StdResult<StateResponse> {
-    let config: State = load(&deps.storage, CONFIG_KEY)?;
-    Ok(StateResponse {
-        score_count: config.score_count,
-        max_size: config.max_size,
-        prng_seed: config.prng_seed,
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::SetPaidReads {
+            status: ResponseStatus::Success,
+        })?),
     })
 }
 
-pub fn query<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>, msg: QueryMsg) -> QueryResult {
-    match msg {
-        QueryMsg::GetStats {} => to_binary(&query_stats(deps)?), // get the max_length allowed and the count
-        QueryMsg::WithPermit { permit, query } => permit_queries(deps, permit, query),
-        _ => authenticated_queries(deps, msg),
+fn try_receive<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    from: HumanAddr,
+    amount: Uint128,
+    msg: Option<Binary>,
+) -> ContractResult<HandleResponse> {
+    let state: State = load_state(&deps.storage)?;
+    let read_fee = state
+        .read_fee
+        .filter(|read_fee| read_fee.token.address == env.message.sender)
+        .ok_or(ContractError::FeeNotAccepted)?;
+    if amount < read_fee.amount {
+        return Err(ContractError::InsufficientFee {
+            amount: amount.u128(),
+            fee: read_fee.amount.u128(),
+        });
     }
-}
-
-fn authenticated_queries<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-    msg: QueryMsg,
-) -> QueryResult {
-    let (addresses, key) = msg.get_validation_params();
 
-    for address in addresses {
-        let canonical_addr = deps.api.canonical_address(address)?;
+    let msg = msg.ok_or(ContractError::ReceiveMsgMissing)?;
+    let messages = match from_binary(&msg)? {
+        ReceiveMsg::PayForRead { borrower } => {
+            pay_for_read(deps, &env, &read_fee, &from, amount, &borrower)?
+        }
+    };
 
-        let expected_key = read_viewing_key(&deps.storage, &canonical_addr);
+    Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::Receive {
+            status: ResponseStatus::Success,
+        })?),
+    })
+}
 
-        if expected_key.is_none() {
-            // Checking the key will take significant time. We don't want to exit immediately if it isn't set
-            // in a way which will allow to time the command and determine if a viewing key doesn't exist
-            key.check_viewing_key(&[0u8; VIEWING_KEY_SIZE]);
-        } else if key.check_viewing_key(expected_key.unwrap().as_slice()) {
-            // return to_binary(&query_read(deps, &address)?)
-            return match msg {
-                QueryMsg::Read { address, .. } => to_binary(&query_read(deps, &address)?),
-                _ => panic!("This query type does not require authentication"),
-            };
+/// Adds a read to the lender's grant on the borrower's score and forwards the payment,
+/// split between the borrower and the treasury.
+fn pay_for_read<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    read_fee: &ReadFee,
+    lender: &HumanAddr,
+    amount: Uint128,
+    borrower: &HumanAddr,
+) -> ContractResult<Vec<CosmosMsg>> {
+    let owner = deps.api.canonical_address(borrower)?;
+    let grantee = deps.api.canonical_address(lender)?;
+    if may_load_user(&deps.storage, &owner)?.is_none() {
+        return Err(ContractError::NotFound);
+    }
+    if !accepts_paid_reads(&deps.storage, &owner) {
+        return Err(ContractError::PaidReadsNotAccepted);
+    }
+    // an unlimited grant has no read left to buy, so the payment would be for nothing
+    if let Some(grant) = read_grant(&deps.storage, &owner, &grantee)? {
+        if grant.max_reads.is_none() && !grant.is_expired(env.block.time) {
+            return Err(ContractError::GrantAlreadyUnlimited);
         }
     }
 
-    Err(StdError::unauthorized())
-}
+    add_read(&mut deps.storage, &owner, &grantee, env.block.time, None)?;
 
-pub fn sha_256(data: &[u8]) -> [u8; SHA256_HASH_SIZE] {
-    let mut hasher = Sha256::new();
-    hasher.update(data);
-    let hash = hasher.finalize();
+    // amount * share / 100, split up so it can't overflow as the share is at most 100
+    let share = u128::from(read_fee.borrower_share);
+    let borrower_amount = amount.u128() / 100 * share + amount.u128() % 100 * share / 100;
+    let payouts = [
+        (borrower.clone(), borrower_amount),
+        (read_fee.treasury.clone(), amount.u128() - borrower_amount),
+    ];
+    let mut messages = vec![];
+    for (recipient, amount) in payouts.iter().filter(|(_, amount)| *amount > 0) {
+        messages.push(transfer_msg(
+            recipient.clone(),
+            Uint128(*amount),
+            None,
+            None,
+            BLOCK_SIZE,
+            read_fee.token.code_hash.clone(),
+            read_fee.token.address.clone(),
+        )?);
+    }
 
-    let mut result = [0u8; 32];
-    result.copy_from_slice(hash.as_slice());
-    result
+    Ok(messages)
 }
 
-fn permit_queries<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-    permit: Permit,
-    query: QueryWithPermit,
-    // env: Env
-) -> Result<Binary, StdError> {
-    // Validate permit content
-    let token_address = ReadonlyConfig::from_storage(&deps.storage)
-        .constants()?
-        .contract_address;
-
-    let account = validate(deps, PREFIX_REVOKED_PERMITS, &permit, token_address)?;
+/// Adds a read to the grant of `grantee`. An unexpired grant keeps its terms, so one without a
+/// read limit stays unlimited. Otherwise a single read until `expires_at` is granted.
+fn add_read<S: Storage>(
+    storage: &mut S,
+    owner: &CanonicalAddr,
+    grantee: &CanonicalAddr,
+    time: u64,
+    expires_at: Option<u64>,
+) -> StdResult<()> {
+    let grant = match read_grant(storage, owner, grantee)? {
+        Some(grant) if !grant.is_expired(time) => Grant {
+            max_reads: grant.max_reads.map(|max_reads| max_reads.saturating_add(1)),
+            ..grant
+        },
+        _ => Grant {
+            expires_at,
+            max_reads: Some(1),
+            reads: 0,
+        },
+    };
+    write_grant(storage, owner, grantee, &grant)
+}
 
-    // Permit validated! We can now execute the query.
-    match query {
-        QueryWithPermit::Balance {} => {
-            if !permit.check_permission(&Permission::Balance) {
-                return Err(StdError::generic_err(format!(
-                    "No permission to query balance (score), got permissions {:?}",
-                    permit.params.permissions
-                )));
-            }
+fn try_subscribe<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    contract: HumanAddr,
+) -> ContractResult<HandleResponse> {
+    let owner = deps.api.canonical_address(&env.message.sender)?;
+    let contract = deps.api.canonical_address(&contract)?;
+    if read_callback_contract(&deps.storage, &contract)?.is_none() {
+        return Err(ContractError::CallbackContractNotFound);
+    }
 
-            to_binary(&query_read(deps, &account))
-        }
+    let mut subscribers = read_subscribers(&deps.storage, &owner)?;
+    subscribers.retain(|subscriber| *subscriber != contract);
+    if subscribers.len() >= MAX_SUBSCRIBERS {
+        return Err(ContractError::TooManySubscribers {
+            max: MAX_SUBSCRIBERS,
+        });
     }
+    subscribers.push(contract);
+    write_subscribers(&mut deps.storage, &owner, &subscribers)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::Subscribe {
+            status: ResponseStatus::Success,
+        })?),
+    })
 }
 
-#[cfg(test)]
-mod tests {
+fn try_unsubscribe<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    contract: HumanAddr,
+) -> ContractResult<HandleResponse> {
+    let owner = deps.api.canonical_address(&env.message.sender)?;
+    let contract = deps.api.canonical_address(&contract)?;
 
-    use super::*;
-    use cosmwasm_std::testing::{mock_dependencies, mock_env};
-    use cosmwasm_std::{coins, from_binary, ReadonlyStorage};
+    let mut subscribers = read_subscribers(&deps.storage, &owner)?;
+    subscribers.retain(|subscriber| *subscriber != contract);
+    write_subscribers(&mut deps.storage, &owner, &subscribers)?;
+    let mut pending = read_pending_callbacks(&deps.storage, &owner)?;
+    pending.retain(|subscriber| *subscriber != contract);
+    write_pending_callbacks(&mut deps.storage, &owner, &pending)?;
 
-    #[test]
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::Unsubscribe {
+            status: ResponseStatus::Success,
+        })?),
+    })
+}
+
+fn try_deliver_callback<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    borrower: HumanAddr,
+    contract: HumanAddr,
+) -> ContractResult<HandleResponse> {
+    let owner = deps.api.canonical_address(&borrower)?;
+    let contract_address = deps.api.canonical_address(&contract)?;
+
+    let mut pending = read_pending_callbacks(&deps.storage, &owner)?;
+    if !pending.contains(&contract_address) {
+        return Err(ContractError::CallbackNotPending);
+    }
+    pending.retain(|subscriber| *subscriber != contract_address);
+    write_pending_callbacks(&mut deps.storage, &owner, &pending)?;
+
+    // nothing is sent if the admin has taken the contract out since
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let code_hash = read_callback_contract(&deps.storage, &contract_address)?;
+    if let (Some(code_hash), Some(user)) = (code_hash, may_load_user(&deps.storage, &owner)?) {
+        let callback = ScoreCallbackMsg::ScoreUpdated {
+            borrower,
+            band: user.band,
+            timestamp: user.timestamp,
+        };
+        messages.push(callback.to_cosmos_msg(code_hash, contract, None)?);
+    }
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::DeliverCallback {
+            status: ResponseStatus::Success,
+        })?),
+    })
+}
+
+fn try_request_score<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    borrower: HumanAddr,
+    purpose: String,
+    expires_at: Option<u64>,
+) -> ContractResult<HandleResponse> {
+    if purpose.len() > MAX_PURPOSE_SIZE {
+        return Err(ContractError::PurposeTooLong {
+            len: purpose.len(),
+            max_size: MAX_PURPOSE_SIZE,
+        });
+    }
+
+    let lender = deps.api.canonical_address(&env.message.sender)?;
+    let borrower = deps.api.canonical_address(&borrower)?;
+
+    let mut requests = read_score_requests(&deps.storage, &borrower)?;
+    requests.retain(|request| request.lender != lender && !request.is_expired(env.block.time));
+    // requests are kept oldest first, so a flood of requests can't lock out new lenders
+    if requests.len() >= MAX_PENDING_REQUESTS {
+        requests.drain(..=requests.len() - MAX_PENDING_REQUESTS);
+    }
+    let max_expires_at = env.block.time.saturating_add(MAX_REQUEST_LIFETIME);
+    requests.push(ScoreRequest {
+        lender,
+        purpose,
+        expires_at: Some(
+            expires_at.map_or(max_expires_at, |expires_at| expires_at.min(max_expires_at)),
+        ),
+        requested_at: env.block.time,
+    });
+    write_score_requests(&mut deps.storage, &borrower, &requests)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::RequestScore {
+            status: ResponseStatus::Success,
+        })?),
+    })
+}
+
+/// Approving a request grants the lender a single read until the request's expiry, or adds
+/// the read to the grant the lender already holds.
+fn try_answer_request<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    lender: HumanAddr,
+    approve: bool,
+) -> ContractResult<HandleResponse> {
+    let borrower = deps.api.canonical_address(&env.message.sender)?;
+    let lender = deps.api.canonical_address(&lender)?;
+
+    let mut requests = read_score_requests(&deps.storage, &borrower)?;
+    let position = requests
+        .iter()
+        .position(|request| request.lender == lender)
+        .ok_or(ContractError::RequestNotFound)?;
+    let request = requests.remove(position);
+    if request.is_expired(env.block.time) {
+        return Err(ContractError::RequestNotFound);
+    }
+    write_score_requests(&mut deps.storage, &borrower, &requests)?;
+
+    let answer = if approve {
+        add_read(
+            &mut deps.storage,
+            &borrower,
+            &lender,
+            env.block.time,
+            request.expires_at,
+        )?;
+
+        HandleAnswer::ApproveRequest {
+            status: ResponseStatus::Success,
+        }
+    } else {
+        HandleAnswer::DenyRequest {
+            status: ResponseStatus::Success,
+        }
+    };
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&answer)?),
+    })
+}
+
+/// Read a score through a grant, counting the read against the grant's `max_reads`.
+fn try_read_as_grantee<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    owner: HumanAddr,
+) -> ContractResult<HandleResponse> {
+    let data = read_as_grantee(deps, &env, &owner, SCORE_FIELDS.to_vec())?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::ReadAsGrantee { data })?),
+    })
+}
+
+fn try_read_score_logged<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    owner: HumanAddr,
+    fields: Option<Vec<ScoreField>>,
+) -> ContractResult<HandleResponse> {
+    let fields = fields.unwrap_or_else(|| SCORE_FIELDS.to_vec());
+    let data = read_as_grantee(deps, &env, &owner, fields)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::ReadScoreLogged { data })?),
+    })
+}
+
+/// Read a score through a grant, counting the read against the grant's `max_reads` and
+/// recording it in the owner's access log.
+fn read_as_grantee<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    owner: &HumanAddr,
+    fields: Vec<ScoreField>,
+) -> ContractResult<ScoreResponse> {
+    let owner_address = deps.api.canonical_address(owner)?;
+    let grantee = deps.api.canonical_address(&env.message.sender)?;
+
+    let mut grant =
+        read_grant(&deps.storage, &owner_address, &grantee)?.ok_or(ContractError::Unauthorized)?;
+    if grant.is_expired(env.block.time) {
+        return Err(ContractError::GrantExpired);
+    }
+    if grant.is_exhausted() {
+        return Err(ContractError::GrantExhausted);
+    }
+
+    grant.reads = grant.reads.saturating_add(1);
+    write_grant(&mut deps.storage, &owner_address, &grantee, &grant)?;
+
+    let score = check_fresh(query_read(deps, owner, env.block.time)?)?;
+    append_access_log(
+        &mut deps.storage,
+        &owner_address,
+        &grantee,
+        env.block.time,
+        fields.clone(),
+    )?;
+
+    Ok(only_fields(score, &fields))
+}
+
+/// Leaves out the parts of the score a grantee didn't ask for.
+fn only_fields(score: ScoreResponse, fields: &[ScoreField]) -> ScoreResponse {
+    let wants = |field| fields.contains(&field);
+
+    ScoreResponse {
+        score: score.score.filter(|_| wants(ScoreField::Score)),
+        percentile: score.percentile.filter(|_| wants(ScoreField::Score)),
+        band: score.band.filter(|_| wants(ScoreField::Band)),
+        description: if wants(ScoreField::Description) {
+            score.description
+        } else {
+            String::from("N/A")
+        },
+        components: score.components.filter(|_| wants(ScoreField::Components)),
+        loan_ceiling: score
+            .loan_ceiling
+            .filter(|_| wants(ScoreField::LoanCeiling)),
+        ..score
+    }
+}
+
+fn check_admin(state: &State, env: &Env) -> ContractResult<()> {
+    if env.message.sender != state.admin {
+        return Err(ContractError::Unauthorized);
+    }
+
+    Ok(())
+}
+
+fn try_set_max_size<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    max_size: u16,
+) -> ContractResult<HandleResponse> {
+    let mut state: State = load_state(&deps.storage)?;
+    check_admin(&state, &env)?;
+
+    state.max_size = valid_max_size(max_size).ok_or(ContractError::InvalidMaxSize)?;
+    save_state(&mut deps.storage, &state)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::SetMaxSize {
+            status: ResponseStatus::Success,
+        })?),
+    })
+}
+
+fn try_set_score_validity<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    score_validity: u64,
+) -> ContractResult<HandleResponse> {
+    let mut state: State = load_state(&deps.storage)?;
+    check_admin(&state, &env)?;
+
+    state.score_validity = score_validity;
+    save_state(&mut deps.storage, &state)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::SetScoreValidity {
+            status: ResponseStatus::Success,
+        })?),
+    })
+}
+
+fn check_status(status: ContractStatus, msg: &HandleMsg) -> ContractResult<()> {
+    let allowed = match status {
+        ContractStatus::Normal => true,
+        ContractStatus::StopRecording => !matches!(msg, HandleMsg::Record { .. }),
+        ContractStatus::StopAll => matches!(
+            msg,
+            HandleMsg::SetContractStatus { .. }
+                | HandleMsg::ChangeAdmin { .. }
+                | HandleMsg::AcceptAdmin { .. }
+                | HandleMsg::RevokePermit { .. }
+                | HandleMsg::RevokeAccess { .. }
+                | HandleMsg::Unsubscribe { .. }
+                | HandleMsg::RemoveCallbackContract { .. }
+                | HandleMsg::SetPaidReads { accept: false, .. }
+                | HandleMsg::DenyRequest { .. }
+                | HandleMsg::DeleteViewingKey { .. }
+                | HandleMsg::DeleteMyScore { .. }
+        ),
+    };
+    if !allowed {
+        return Err(ContractError::ContractStopped);
+    }
+
+    Ok(())
+}
+
+fn try_set_contract_status<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    level: ContractStatus,
+) -> ContractResult<HandleResponse> {
+    let mut state: State = load_state(&deps.storage)?;
+    check_admin(&state, &env)?;
+
+    state.status = level;
+    save_state(&mut deps.storage, &state)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::SetContractStatus {
+            status: ResponseStatus::Success,
+        })?),
+    })
+}
+
+fn try_set_k_anonymity<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    k_anonymity: u64,
+) -> ContractResult<HandleResponse> {
+    let mut state: State = load_state(&deps.storage)?;
+    check_admin(&state, &env)?;
+
+    state.k_anonymity = k_anonymity;
+    save_state(&mut deps.storage, &state)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::SetKAnonymity {
+            status: ResponseStatus::Success,
+        })?),
+    })
+}
+
+fn try_set_read_fee<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    read_fee: Option<ReadFee>,
+) -> ContractResult<HandleResponse> {
+    let mut state: State = load_state(&deps.storage)?;
+    check_admin(&state, &env)?;
+
+    let messages = match &read_fee {
+        Some(read_fee) => vec![register_fee_token(read_fee, &env)?],
+        None => vec![],
+    };
+    state.read_fee = read_fee;
+    save_state(&mut deps.storage, &state)?;
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::SetReadFee {
+            status: ResponseStatus::Success,
+        })?),
+    })
+}
+
+fn try_set_record_fee<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    record_fee: Option<Coin>,
+) -> ContractResult<HandleResponse> {
+    let mut state: State = load_state(&deps.storage)?;
+    check_admin(&state, &env)?;
+
+    state.record_fee = record_fee;
+    save_state(&mut deps.storage, &state)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::SetRecordFee {
+            status: ResponseStatus::Success,
+        })?),
+    })
+}
+
+fn try_withdraw<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    amount: Vec<Coin>,
+    recipient: HumanAddr,
+) -> ContractResult<HandleResponse> {
+    let state: State = load_state(&deps.storage)?;
+    check_admin(&state, &env)?;
+
+    Ok(HandleResponse {
+        messages: vec![CosmosMsg::Bank(BankMsg::Send {
+            from_address: env.contract.address,
+            to_address: recipient,
+            amount,
+        })],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::Withdraw {
+            status: ResponseStatus::Success,
+        })?),
+    })
+}
+
+fn try_set_score_range<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    min_score: u64,
+    max_score: u64,
+) -> ContractResult<HandleResponse> {
+    let mut state: State = load_state(&deps.storage)?;
+    check_admin(&state, &env)?;
+    check_score_range(min_score, max_score)?;
+
+    state.min_score = min_score;
+    state.max_score = max_score;
+    save_state(&mut deps.storage, &state)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::SetScoreRange {
+            status: ResponseStatus::Success,
+        })?),
+    })
+}
+
+/// Upgrade the state and the records of `addresses` to the current storage layout, and bump
+/// the stored contract version. Large deployments are migrated over several calls.
+///
+/// A state that predates versioning has no admin. It is claimed by the first sender that
+/// knows the `prng_seed` the contract was instantiated with, which was only ever sent
+/// encrypted by the deployer.
+fn try_migrate_storage<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    addresses: Vec<HumanAddr>,
+    legacy_prng_seed: Option<String>,
+) -> ContractResult<HandleResponse> {
+    match may_load_legacy_state(&deps.storage)? {
+        Some(legacy) => {
+            let seed = legacy_prng_seed.ok_or(ContractError::Unauthorized)?;
+            if sha_256(base64::encode(seed).as_bytes()).to_vec() != legacy.prng_seed {
+                return Err(ContractError::Unauthorized);
+            }
+            save_state(&mut deps.storage, &legacy.upgrade(env.message.sender))?;
+        }
+        None => check_admin(&load_state(&deps.storage)?, &env)?,
+    }
+
+    let mut migrated = 0_u32;
+    for address in &addresses {
+        let address = deps.api.canonical_address(address)?;
+        if migrate_user(&mut deps.storage, &address)? {
+            migrated += 1;
+        }
+    }
+
+    Config::from_storage(&mut deps.storage).set_contract_version(&ContractVersion {
+        name: CONTRACT_NAME.to_string(),
+        version: CONTRACT_VERSION.to_string(),
+    })?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::MigrateStorage { migrated })?),
+    })
+}
+
+/// Nominate a new admin. The nominee has to confirm with `AcceptAdmin` before the
+/// handover takes effect, so a mistyped address cannot lock the contract.
+fn try_change_admin<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    address: HumanAddr,
+) -> ContractResult<HandleResponse> {
+    let mut state: State = load_state(&deps.storage)?;
+    check_admin(&state, &env)?;
+
+    state.pending_admin = Some(address);
+    save_state(&mut deps.storage, &state)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::ChangeAdmin {
+            status: ResponseStatus::Success,
+        })?),
+    })
+}
+
+fn try_accept_admin<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> ContractResult<HandleResponse> {
+    let mut state: State = load_state(&deps.storage)?;
+    if state.pending_admin.as_ref() != Some(&env.message.sender) {
+        return Err(ContractError::Unauthorized);
+    }
+
+    state.admin = env.message.sender;
+    state.pending_admin = None;
+    save_state(&mut deps.storage, &state)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::AcceptAdmin {
+            status: ResponseStatus::Success,
+        })?),
+    })
+}
+
+fn try_add_oracle<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    address: HumanAddr,
+) -> ContractResult<HandleResponse> {
+    let state: State = load_state(&deps.storage)?;
+    check_admin(&state, &env)?;
+
+    let oracle = deps.api.canonical_address(&address)?;
+    write_oracle(&mut deps.storage, &oracle);
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::AddOracle {
+            status: ResponseStatus::Success,
+        })?),
+    })
+}
+
+fn try_remove_oracle<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    address: HumanAddr,
+) -> ContractResult<HandleResponse> {
+    let state: State = load_state(&deps.storage)?;
+    check_admin(&state, &env)?;
+
+    let oracle = deps.api.canonical_address(&address)?;
+    remove_oracle(&mut deps.storage, &oracle);
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::RemoveOracle {
+            status: ResponseStatus::Success,
+        })?),
+    })
+}
+
+fn try_add_callback_contract<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    contract: HumanAddr,
+    code_hash: String,
+) -> ContractResult<HandleResponse> {
+    let state: State = load_state(&deps.storage)?;
+    check_admin(&state, &env)?;
+
+    let contract = deps.api.canonical_address(&contract)?;
+    write_callback_contract(&mut deps.storage, &contract, &code_hash)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::AddCallbackContract {
+            status: ResponseStatus::Success,
+        })?),
+    })
+}
+
+/// Subscriptions to the contract are kept, but no callbacks are sent to it until it is added
+/// again.
+fn try_remove_callback_contract<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    contract: HumanAddr,
+) -> ContractResult<HandleResponse> {
+    let state: State = load_state(&deps.storage)?;
+    check_admin(&state, &env)?;
+
+    let contract = deps.api.canonical_address(&contract)?;
+    remove_callback_contract(&mut deps.storage, &contract);
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::RemoveCallbackContract {
+            status: ResponseStatus::Success,
+        })?),
+    })
+}
+
+fn try_add_oracle_key<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    oracle: HumanAddr,
+    pubkey: Binary,
+) -> ContractResult<HandleResponse> {
+    let state: State = load_state(&deps.storage)?;
+    check_admin(&state, &env)?;
+
+    PublicKey::parse(pubkey.as_slice()).map_err(|err| ContractError::AttestationInvalid {
+        reason: format!("malformed public key, {}", err),
+    })?;
+    write_oracle_key(&mut deps.storage, pubkey.as_slice(), &oracle)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::AddOracleKey {
+            status: ResponseStatus::Success,
+        })?),
+    })
+}
+
+fn try_remove_oracle_key<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    pubkey: Binary,
+) -> ContractResult<HandleResponse> {
+    let state: State = load_state(&deps.storage)?;
+    check_admin(&state, &env)?;
+
+    remove_oracle_key(&mut deps.storage, pubkey.as_slice());
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::RemoveOracleKey {
+            status: ResponseStatus::Success,
+        })?),
+    })
+}
+
+/// Checks an oracle's signature over the record and consumes its nonce. Returns the oracle
+/// address the score is credited to.
+fn verify_attestation<S: Storage>(
+    storage: &mut S,
+    payload: &AttestationPayload,
+    attestation: &ScoreAttestation,
+    now: u64,
+    score_validity: u64,
+) -> ContractResult<HumanAddr> {
+    let pubkey = attestation.pubkey.as_slice();
+    let oracle = read_oracle_key(storage, pubkey)?.ok_or(ContractError::Unauthorized)?;
+
+    if attestation.timestamp > now {
+        return Err(ContractError::AttestationInvalid {
+            reason: String::from("timestamp is in the future"),
+        });
+    }
+    if now - attestation.timestamp >= score_validity {
+        return Err(ContractError::AttestationExpired);
+    }
+
+    let message = sha_256(to_binary(payload)?.as_slice());
+    let public_key = PublicKey::parse(pubkey).map_err(|err| ContractError::AttestationInvalid {
+        reason: format!("malformed public key, {}", err),
+    })?;
+    let signature = Signature::parse_slice(attestation.signature.as_slice()).map_err(|err| {
+        ContractError::AttestationInvalid {
+            reason: format!("malformed signature, {}", err),
+        }
+    })?;
+    if !public_key.verify(&message, signature) {
+        return Err(ContractError::AttestationInvalid {
+            reason: String::from("signature does not match the record"),
+        });
+    }
+
+    if !use_nonce(storage, pubkey, attestation.nonce) {
+        return Err(ContractError::AttestationReplayed);
+    }
+
+    Ok(oracle)
+}
+
+fn check_record_fee(state: &State, env: &Env) -> ContractResult<()> {
+    if let Some(fee) = &state.record_fee {
+        let paid = env
+            .message
+            .sent_funds
+            .iter()
+            .any(|coin| coin.denom == fee.denom && coin.amount >= fee.amount);
+        if !paid {
+            return Err(ContractError::RecordFeeUnpaid {
+                amount: fee.amount.u128(),
+                denom: fee.denom.clone(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn try_record<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    subject: HumanAddr,
+    score: u64,
+    band: ScoreBand,
+    description: String,
+    components: Vec<ScoreComponent>,
+    loan_ceiling: Option<u64>,
+    attestation: Option<ScoreAttestation>,
+) -> ContractResult<HandleResponse> {
+    let state: State = load_state(&deps.storage)?;
+    check_record_fee(&state, &env)?;
+
+    let (oracle, timestamp) = match attestation {
+        // anyone may submit a score signed by a registered oracle key
+        Some(attestation) => {
+            // an older attestation must not replace a newer score
+            let subject_address = deps.api.canonical_address(&subject)?;
+            if let Some(previous) = may_load_user(&deps.storage, &subject_address)? {
+                if attestation.timestamp <= previous.timestamp {
+                    return Err(ContractError::AttestationOutdated);
+                }
+            }
+
+            let payload = AttestationPayload {
+                chain_id: env.block.chain_id.clone(),
+                contract: env.contract.address.clone(),
+                subject: subject.clone(),
+                score,
+                band,
+                description: description.clone(),
+                components: components.clone(),
+                loan_ceiling,
+                timestamp: attestation.timestamp,
+                nonce: attestation.nonce,
+            };
+            let oracle = verify_attestation(
+                &mut deps.storage,
+                &payload,
+                &attestation,
+                env.block.time,
+                state.score_validity,
+            )?;
+            (oracle, attestation.timestamp)
+        }
+        // otherwise only registered scoring oracles may write scores
+        None => {
+            let oracle_address = deps.api.canonical_address(&env.message.sender)?;
+            if !is_oracle(&deps.storage, &oracle_address) {
+                return Err(ContractError::Unauthorized);
+            }
+            (env.message.sender, env.block.time)
+        }
+    };
+
+    if score < state.min_score || score > state.max_score {
+        return Err(ContractError::ScoreOutOfRange {
+            score,
+            min_score: state.min_score,
+            max_score: state.max_score,
+        });
+    }
+    if description.len() > state.max_size as usize {
+        return Err(ContractError::DescriptionTooLong {
+            len: description.len(),
+            max_size: state.max_size,
+        });
+    }
+
+    let total_weight: u64 = components.iter().map(|c| c.weight as u64).sum();
+    if total_weight > 100 {
+        return Err(ContractError::InvalidComponents { total_weight });
+    }
+
+    let subject_address = deps.api.canonical_address(&subject)?;
+    let previous = may_load_counted_user(&deps.storage, &subject_address)?;
+    let description = description.as_bytes();
+    // create the User struct containing score, its breakdown and timestamp
+    let stored_score = User {
+        score,
+        timestamp,
+        description: description.to_vec(),
+        band,
+        components,
+        loan_ceiling,
+    };
+
+    save_user(&mut deps.storage, &subject_address, &stored_score)?;
+    append_score_history(
+        &mut deps.storage,
+        &subject_address,
+        &ScoreEntry {
+            score,
+            timestamp,
+            description: description.to_vec(),
+            oracle,
+        },
+    )?;
+
+    let mut config = Config::from_storage(&mut deps.storage);
+    let mut distribution = config.distribution()?;
+    if let Some((previous, true)) = &previous {
+        distribution.remove(previous);
+    }
+    distribution.insert(&stored_score);
+    config.set_distribution(&distribution)?;
+
+    // callbacks are only queued, a subscriber that fails must not be able to hold up recording
+    let mut pending = read_pending_callbacks(&deps.storage, &subject_address)?;
+    for subscriber in read_subscribers(&deps.storage, &subject_address)? {
+        if !pending.contains(&subscriber) {
+            pending.push(subscriber);
+        }
+    }
+    write_pending_callbacks(&mut deps.storage, &subject_address, &pending)?;
+
+    let status: String = String::from("Score recorded!");
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::Record { status })?),
+    })
+}
+
+fn query_read<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    now: u64,
+) -> ContractResult<ScoreResponse> {
+    let status: String;
+    let description: String;
+    let mut score: Option<u64> = None;
+    let mut timestamp: Option<u64> = None;
+    let mut expires_at: Option<u64> = None;
+    let mut is_stale = false;
+    let mut band: Option<ScoreBand> = None;
+    let mut components: Option<Vec<ScoreComponent>> = None;
+    let mut loan_ceiling: Option<u64> = None;
+    let mut percentile: Option<u8> = None;
+    let sender_address = deps.api.canonical_address(address)?;
+    let result: Option<User> = may_load_user(&deps.storage, &sender_address)?;
+
+    match result {
+        Some(stored_score) => {
+            let config: State = load_state(&deps.storage)?;
+            let valid_until = stored_score.timestamp.saturating_add(config.score_validity);
+
+            score = Some(stored_score.score);
+            timestamp = Some(stored_score.timestamp);
+            expires_at = Some(valid_until);
+            is_stale = now >= valid_until;
+            band = Some(stored_score.band);
+            components = Some(stored_score.components);
+            loan_ceiling = stored_score.loan_ceiling;
+            let distribution = ReadonlyConfig::from_storage(&deps.storage).distribution()?;
+            if distribution.count() >= config.k_anonymity {
+                percentile = distribution.percentile(stored_score.score);
+            }
+            description = String::from_utf8(stored_score.description)
+                .map_err(|err| StdError::invalid_utf8(err.to_string()))?;
+            status = if is_stale {
+                String::from("Score found, but it has expired.")
+            } else {
+                String::from("Score found.")
+            };
+        }
+        None => {
+            status = String::from("Score not found.");
+            description = String::from("N/A");
+            return Ok(ScoreResponse {
+                status,
+                timestamp,
+                score,
+                description,
+                expires_at,
+                is_stale,
+                as_of: now,
+                band,
+                components,
+                loan_ceiling,
+                percentile,
+            });
+        }
+    }
+
+    Ok(ScoreResponse {
+        score,
+        timestamp,
+        status,
+        description,
+        expires_at,
+        is_stale,
+        as_of: now,
+        band,
+        components,
+        loan_ceiling,
+        percentile,
+    })
+}
+
+/// Lenders only ever get to see scores that exist and are still within the validity window.
+fn check_fresh(score: ScoreResponse) -> ContractResult<ScoreResponse> {
+    if score.score.is_none() {
+        return Err(ContractError::NotFound);
+    }
+    if score.is_stale {
+        return Err(ContractError::ScoreExpired);
+    }
+
+    Ok(score)
+}
+
+/// Hides what the score is based on from holders of a `history` permit.
+fn without_breakdown(score: ScoreResponse) -> ScoreResponse {
+    ScoreResponse {
+        description: String::from("N/A"),
+        components: None,
+        loan_ceiling: None,
+        ..score
+    }
+}
+
+/// Leaves holders of a `balance` permit only the band and how fresh it is.
+fn band_only(score: ScoreResponse) -> BandResponse {
+    BandResponse {
+        band: score.band,
+        timestamp: score.timestamp,
+        expires_at: score.expires_at,
+        is_stale: score.is_stale,
+        as_of: score.as_of,
+    }
+}
+
+fn query_grants<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    owner: &HumanAddr,
+) -> ContractResult<GrantsResponse> {
+    let owner = deps.api.canonical_address(owner)?;
+
+    let grants = list_grants(&deps.storage, &owner)?
+        .into_iter()
+        .map(|(grantee, grant)| {
+            Ok(GrantInfo {
+                grantee: deps.api.human_address(&grantee)?,
+                expires_at: grant.expires_at,
+                max_reads: grant.max_reads,
+                reads: grant.reads,
+            })
+        })
+        .collect::<StdResult<Vec<GrantInfo>>>()?;
+
+    Ok(GrantsResponse { grants })
+}
+
+fn query_history<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    page: u32,
+    page_size: u32,
+) -> ContractResult<HistoryResponse> {
+    let owner = deps.api.canonical_address(address)?;
+    let (entries, total) = get_score_history(&deps.storage, &owner, page, page_size)?;
+
+    let entries = entries
+        .into_iter()
+        .map(|entry| ScoreHistoryEntry {
+            score: entry.score,
+            timestamp: entry.timestamp,
+            description: String::from_utf8_lossy(&entry.description).into_owned(),
+            oracle: entry.oracle,
+        })
+        .collect();
+
+    Ok(HistoryResponse { entries, total })
+}
+
+/// Whether the borrower qualifies, authorized by an eligibility permit the borrower signed.
+/// Whoever holds the permit may ask, since queries don't tell who is asking.
+fn query_eligibility<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    borrower: &HumanAddr,
+    min_band: ScoreBand,
+    max_age: Option<u64>,
+    auth: &Permit,
+) -> ContractResult<EligibilityResponse> {
+    check_permit_purpose(auth, true)?;
+    let signer = validate_permit(deps, auth)?;
+    if signer != *borrower {
+        return Err(ContractError::Unauthorized);
+    }
+    check_permission(auth, Permission::Balance, "verify eligibility")?;
+
+    check_eligibility(deps, borrower, min_band, max_age)
+}
+
+fn check_eligibility<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    borrower: &HumanAddr,
+    min_band: ScoreBand,
+    max_age: Option<u64>,
+) -> ContractResult<EligibilityResponse> {
+    let state: State = load_state(&deps.storage)?;
+    let now = load_block_time(&deps.storage)?;
+    let user = may_load_user(&deps.storage, &deps.api.canonical_address(borrower)?)?;
+
+    Ok(match user {
+        Some(user) => {
+            let age = now.saturating_sub(user.timestamp);
+            EligibilityResponse {
+                eligible: user.band >= min_band
+                    && age < state.score_validity
+                    && age <= max_age.unwrap_or(state.score_validity),
+                band: Some(user.band),
+            }
+        }
+        None => EligibilityResponse {
+            eligible: false,
+            band: None,
+        },
+    })
+}
+
+/// Read a score through a grant. Queries cannot update the read counter, so grants with a
+/// `max_reads` limit have to be used through the `ReadAsGrantee` handle message instead.
+fn query_read_as_grantee<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    owner: &HumanAddr,
+    grantee: &HumanAddr,
+) -> ContractResult<ScoreResponse> {
+    let owner_address = deps.api.canonical_address(owner)?;
+    let grantee = deps.api.canonical_address(grantee)?;
+
+    let grant =
+        read_grant(&deps.storage, &owner_address, &grantee)?.ok_or(ContractError::Unauthorized)?;
+    let now = load_block_time(&deps.storage)?;
+    if grant.is_expired(now) {
+        return Err(ContractError::GrantExpired);
+    }
+    if grant.max_reads.is_some() {
+        return Err(ContractError::GrantReadLimited);
+    }
+
+    check_fresh(query_read(deps, owner, now)?)
+}
+
+fn query_viewing_keys<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+) -> ContractResult<ViewingKeysResponse> {
+    let owner = deps.api.canonical_address(address)?;
+
+    Ok(ViewingKeysResponse {
+        has_unnamed: read_viewing_key(&deps.storage, &owner).is_some(),
+        names: read_viewing_key_names(&deps.storage, &owner)?,
+    })
+}
+
+fn query_list_scores<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    account: &HumanAddr,
+    start: Option<u32>,
+    limit: Option<u32>,
+) -> ContractResult<ListScoresResponse> {
+    let state: State = load_state(&deps.storage)?;
+    if *account != state.admin {
+        return Err(ContractError::Unauthorized);
+    }
+
+    let start = start.unwrap_or(0);
+    let limit = limit.unwrap_or(DEFAULT_LIST_LIMIT).min(MAX_LIST_LIMIT);
+    let (addresses, len) = get_user_index(&deps.storage, start, limit)?;
+    let next = start.saturating_add(limit);
+
+    let mut scores = vec![];
+    for address in addresses {
+        let user = may_load_user(&deps.storage, &address)?.ok_or(ContractError::NotFound)?;
+        let (latest, _) = get_score_history(&deps.storage, &address, 0, 1)?;
+        scores.push(ScoreMetadata {
+            address: deps.api.human_address(&address)?,
+            timestamp: user.timestamp,
+            oracle: latest.into_iter().next().map(|entry| entry.oracle),
+            band: user.band,
+        });
+    }
+
+    Ok(ListScoresResponse {
+        scores,
+        total: user_count(&deps.storage)?,
+        next: if next < len { Some(next) } else { None },
+    })
+}
+
+fn query_access_log<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    page: u32,
+    page_size: u32,
+) -> ContractResult<AccessLogResponse> {
+    let owner = deps.api.canonical_address(address)?;
+    let (entries, total) = get_access_log(&deps.storage, &owner, page, page_size)?;
+
+    let entries = entries
+        .into_iter()
+        .map(|entry| {
+            Ok(AccessLogEntryInfo {
+                reader: deps.api.human_address(&entry.reader)?,
+                timestamp: entry.timestamp,
+                fields: entry.fields,
+                hash: Binary(entry.hash),
+            })
+        })
+        .collect::<StdResult<Vec<AccessLogEntryInfo>>>()?;
+
+    Ok(AccessLogResponse { entries, total })
+}
+
+fn query_pending_requests<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+) -> ContractResult<PendingRequestsResponse> {
+    let borrower = deps.api.canonical_address(address)?;
+    let now = load_block_time(&deps.storage)?;
+
+    let requests = read_score_requests(&deps.storage, &borrower)?
+        .into_iter()
+        .filter(|request| !request.is_expired(now))
+        .map(|request| {
+            Ok(ScoreRequestInfo {
+                lender: deps.api.human_address(&request.lender)?,
+                purpose: request.purpose,
+                expires_at: request.expires_at,
+                requested_at: request.requested_at,
+            })
+        })
+        .collect::<StdResult<Vec<ScoreRequestInfo>>>()?;
+
+    Ok(PendingRequestsResponse { requests })
+}
+
+fn query_stats<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> ContractResult<StatsResponse> {
+    let config: State = load_state(&deps.storage)?;
+    let distribution = ReadonlyConfig::from_storage(&deps.storage).distribution()?;
+    let now = load_block_time(&deps.storage)?;
+
+    let stale_count = distribution.stale_count(now, config.score_validity);
+    let count = distribution.count();
+    let mean_score = if count > 0 && count >= config.k_anonymity {
+        let mean = distribution.sum / count;
+        Some((mean + MEAN_SCORE_PRECISION / 2) / MEAN_SCORE_PRECISION * MEAN_SCORE_PRECISION)
+    } else {
+        None
+    };
+    let band_counts: Vec<u64> = SCORE_BANDS
+        .iter()
+        .map(|&band| distribution.band_count(band))
+        .collect();
+    let band_counts = k_anonymous_counts(&band_counts, config.k_anonymity);
+    let freshness = k_anonymous_counts(
+        &[count.saturating_sub(stale_count), stale_count],
+        config.k_anonymity,
+    );
+
+    Ok(StatsResponse {
+        contract_status: config.status,
+        score_count: user_count(&deps.storage)?,
+        max_size: config.max_size,
+        score_validity: config.score_validity,
+        min_score: config.min_score,
+        max_score: config.max_score,
+        k_anonymity: config.k_anonymity,
+        read_fee: config.read_fee.clone(),
+        record_fee: config.record_fee.clone(),
+        mean_score,
+        bands: SCORE_BANDS
+            .iter()
+            .zip(band_counts)
+            .map(|(&band, count)| BandCount { band, count })
+            .collect(),
+        fresh_count: freshness[0],
+        stale_count: freshness[1],
+    })
+}
+
+/// Withholds the counts of fewer than `k` users, since they could single those users out.
+/// The counts add up to `score_count`, so further counts are withheld, smallest first, until
+/// the withheld ones add up to at least `k` users and can't be recovered by subtraction.
+fn k_anonymous_counts(counts: &[u64], k: u64) -> Vec<Option<u64>> {
+    let mut withheld: Vec<bool> = counts.iter().map(|&count| count > 0 && count < k).collect();
+    loop {
+        let withheld_sum: u64 = counts
+            .iter()
+            .zip(&withheld)
+            .filter(|(_, &withheld)| withheld)
+            .map(|(&count, _)| count)
+            .sum();
+        if withheld_sum == 0 || withheld_sum >= k {
+            break;
+        }
+        let smallest = counts
+            .iter()
+            .enumerate()
+            .filter(|&(i, &count)| !withheld[i] && count > 0)
+            .min_by_key(|&(_, &count)| count);
+        match smallest {
+            Some((i, _)) => withheld[i] = true,
+            // fewer than `k` users in total, which `score_count` discloses anyway
+            None => break,
+        }
+    }
+
+    counts
+        .iter()
+        .zip(withheld)
+        .map(|(&count, withheld)| if withheld { None } else { Some(count) })
+        .collect()
+}
+
+pub fn query<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>, msg: QueryMsg) -> QueryResult {
+    // stats stay available so everyone can see the contract is stopped
+    let state: State = load_state(&deps.storage)?;
+    if state.status == ContractStatus::StopAll && !matches!(msg, QueryMsg::GetStats {}) {
+        return Err(ContractError::ContractStopped.into());
+    }
+
+    let response = match msg {
+        QueryMsg::GetStats {} => Ok(to_binary(&query_stats(deps)?)?), // get the max_length allowed and the count
+        QueryMsg::WithPermit { permit, query } => permit_queries(deps, permit, query),
+        QueryMsg::VerifyEligibility {
+            borrower,
+            min_band,
+            max_age,
+            auth,
+        } => Ok(to_binary(&query_eligibility(
+            deps, &borrower, min_band, max_age, &auth,
+        )?)?),
+        _ => authenticated_queries(deps, msg),
+    };
+
+    Ok(response?)
+}
+
+fn authenticated_queries<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    msg: QueryMsg,
+) -> ContractResult<Binary> {
+    let (addresses, key) = msg.get_validation_params();
+
+    for address in addresses {
+        let canonical_addr = deps.api.canonical_address(address)?;
+
+        let expected_keys = read_viewing_keys(&deps.storage, &canonical_addr)?;
+
+        if expected_keys.is_empty() {
+            // Checking the key will take significant time. We don't want to exit immediately if it isn't set
+            // in a way which will allow to time the command and determine if a viewing key doesn't exist
+            key.check_viewing_key(&[0u8; VIEWING_KEY_SIZE]);
+            continue;
+        }
+
+        // check every key, so the time taken doesn't tell which of them matched
+        let matched = expected_keys.iter().fold(false, |matched, expected_key| {
+            key.check_viewing_key(expected_key.as_slice()) | matched
+        });
+        if matched {
+            let response = match msg {
+                QueryMsg::Read { address, .. } => to_binary(&query_read(
+                    deps,
+                    &address,
+                    load_block_time(&deps.storage)?,
+                )?),
+                QueryMsg::ReadHistory {
+                    address,
+                    page,
+                    page_size,
+                    ..
+                } => to_binary(&query_history(
+                    deps,
+                    &address,
+                    page.unwrap_or(0),
+                    page_size,
+                )?),
+                QueryMsg::ReadAsGrantee { owner, grantee, .. } => {
+                    to_binary(&query_read_as_grantee(deps, &owner, &grantee)?)
+                }
+                QueryMsg::ListViewingKeys { address, .. } => {
+                    to_binary(&query_viewing_keys(deps, &address)?)
+                }
+                QueryMsg::ListScores {
+                    address,
+                    start,
+                    limit,
+                    ..
+                } => to_binary(&query_list_scores(deps, &address, start, limit)?),
+                QueryMsg::AccessLog {
+                    address,
+                    page,
+                    page_size,
+                    ..
+                } => to_binary(&query_access_log(
+                    deps,
+                    &address,
+                    page.unwrap_or(0),
+                    page_size,
+                )?),
+                QueryMsg::PendingRequests { address, .. } => {
+                    to_binary(&query_pending_requests(deps, &address)?)
+                }
+                _ => panic!("This query type does not require authentication"),
+            };
+            return Ok(response?);
+        }
+    }
+
+    Err(ContractError::Unauthorized)
+}
+
+pub fn sha_256(data: &[u8]) -> [u8; SHA256_HASH_SIZE] {
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    let hash = hasher.finalize();
+
+    let mut result = [0u8; 32];
+    result.copy_from_slice(&hash);
+    result
+}
+
+fn permit_queries<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    permit: Permit,
+    query: QueryWithPermit,
+) -> ContractResult<Binary> {
+    check_permit_purpose(&permit, false)?;
+    let account = validate_permit(deps, &permit)?;
+
+    // Permit validated! We can now execute the query.
+    match query {
+        QueryWithPermit::Band {} => {
+            check_permission(&permit, Permission::Balance, "query score band")?;
+
+            let now = load_block_time(&deps.storage)?;
+            Ok(to_binary(&band_only(query_read(deps, &account, now)?))?)
+        }
+        QueryWithPermit::Balance {} => {
+            check_permission(&permit, Permission::History, "query score")?;
+
+            let now = load_block_time(&deps.storage)?;
+            Ok(to_binary(&without_breakdown(query_read(
+                deps, &account, now,
+            )?))?)
+        }
+        QueryWithPermit::Breakdown {} => {
+            check_permission(&permit, Permission::Owner, "query score breakdown")?;
+
+            let now = load_block_time(&deps.storage)?;
+            Ok(to_binary(&query_read(deps, &account, now)?)?)
+        }
+        QueryWithPermit::History { page, page_size } => {
+            check_permission(&permit, Permission::History, "query score history")?;
+
+            Ok(to_binary(&query_history(
+                deps,
+                &account,
+                page.unwrap_or(0),
+                page_size,
+            )?)?)
+        }
+        QueryWithPermit::Grants {} => {
+            check_permission(&permit, Permission::Allowance, "query grants")?;
+
+            Ok(to_binary(&query_grants(deps, &account)?)?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::msg::Snip20Token;
+    use crate::state::{
+        save, StateV1, UserV1, CONFIG_KEY, PREFIX_GRANTEES, PREFIX_VIEWING_KEY_NAMES,
+        SECONDS_PER_DAY,
+    };
+    use cosmwasm_std::testing::{mock_dependencies, mock_env};
+    use cosmwasm_std::{coins, from_binary, ReadonlyStorage};
+    use cosmwasm_storage::ReadonlyPrefixedStorage;
+    use secret_toolkit::serialization::{Bincode2, Serde};
+
+    fn default_init_msg() -> InitMsg {
+        InitMsg {
+            admin: None,
+            max_size: 10000,
+            score_validity: None,
+            min_score: None,
+            max_score: None,
+            k_anonymity: None,
+            read_fee: None,
+            record_fee: None,
+            prng_seed: "this is a padding".to_string(),
+            oracles: Some(vec![HumanAddr("oracle".to_string())]),
+        }
+    }
+
+    #[test]
     fn init_recore_query() {
         // First we init
         let mut deps = mock_dependencies(20, &coins(2, "token"));
-        let init_msg = InitMsg {
-            max_size: 10000,
-            prng_seed: "this is a padding".to_string(),
+        let init_msg = default_init_msg();
+        let env = mock_env("creator", &coins(20, "token"));
+        let res = init(&mut deps, env, init_msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        // WE RECORD THE SCORE
+        let _env = mock_env("oracle", &coins(20, "token"));
+        let msg = HandleMsg::Record {
+            subject: HumanAddr("creator".to_string()),
+            score: 300,
+            band: ScoreBand::Poor,
+            description: "This describes your score".to_string(),
+            components: None,
+            loan_ceiling: None,
+            attestation: None,
+        };
+        let record_res = handle(&mut deps, _env, msg).unwrap();
+        assert_eq!(0, record_res.messages.len());
+    }
+
+    #[test]
+    fn stats_increment() {
+        // First we init
+        let mut deps = mock_dependencies(20, &coins(20, "token"));
+        let init_msg = default_init_msg();
+        let env = mock_env("creator", &coins(20, "token"));
+        let res = init(&mut deps, env, init_msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        // WE RECORD THE SCORE
+        let _env = mock_env("oracle", &coins(20, "token"));
+        let msg = HandleMsg::Record {
+            subject: HumanAddr("submitter".to_string()),
+            score: 300,
+            band: ScoreBand::Poor,
+            description: "This describes your score".to_string(),
+            components: None,
+            loan_ceiling: None,
+            attestation: None,
+        };
+
+        handle(&mut deps, _env, msg).unwrap();
+
+        // WE QUERY THE STATS
+        let stats_msg = QueryMsg::GetStats {};
+        let stats_res = query(&deps, stats_msg).unwrap();
+        let value: StatsResponse = from_binary(&stats_res).unwrap();
+        assert_eq!(1, value.score_count);
+
+        // WE RECORD THE SCORE A 2ND TIME WITH SAME USER
+        let __env = mock_env("oracle", &coins(20, "token"));
+        let msg = HandleMsg::Record {
+            subject: HumanAddr("submitter".to_string()),
+            score: 400,
+            band: ScoreBand::Poor,
+            description: "This describes your 2nd score".to_string(),
+            components: None,
+            loan_ceiling: None,
+            attestation: None,
+        };
+        handle(&mut deps, __env, msg).unwrap();
+
+        // WE QUERY THE STATS again
+        let stats_msg2 = QueryMsg::GetStats {};
+        let stats_res2 = query(&deps, stats_msg2).unwrap();
+        let value2: StatsResponse = from_binary(&stats_res2).unwrap();
+        assert_eq!(1, value2.score_count);
+    }
+
+    #[test]
+    fn handle_revoke_permit() {
+        // First we init
+        let mut deps = mock_dependencies(45, &coins(2, "token"));
+        let init_msg = default_init_msg();
+        let env = mock_env(
+            "secret1nl7dnjcs9w2a4mn4q43nwyptf3uyllp3xh44j0",
+            &coins(20, "token"),
+        );
+        let res = init(&mut deps, env, init_msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        // WE RECORD THE SCORE
+        let _env = mock_env("oracle", &coins(20, "token"));
+        let msg = HandleMsg::Record {
+            subject: HumanAddr("secret1nl7dnjcs9w2a4mn4q43nwyptf3uyllp3xh44j0".to_string()),
+            score: 300,
+            band: ScoreBand::Poor,
+            description: String::from("Good job dude"),
+            components: None,
+            loan_ceiling: None,
+            attestation: None,
+        };
+        let record_res = handle(&mut deps, _env, msg).unwrap();
+        assert_eq!(0, record_res.messages.len());
+
+        // Revoke a permission
+        let __env = mock_env("creator", &coins(20, "token"));
+        let revoke_msg = HandleMsg::RevokePermit {
+            permit_name: String::from("test"),
+            padding: None,
+        };
+
+        let record_revoke = handle(&mut deps, __env, revoke_msg).unwrap();
+
+        assert_eq!(0, record_revoke.messages.len());
+
+        // Check if permit is revoked
+        let storage_key = PREFIX_REVOKED_PERMITS.to_string() + "creator" + "test";
+
+        let revoked_permits = deps.storage.get(storage_key.as_bytes()).is_some();
+
+        assert!(revoked_permits);
+    }
+
+    #[test]
+    fn handle_viewing_key() {
+        // First we init
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let init_msg = default_init_msg();
+        let env = mock_env("creator", &coins(20, "token"));
+        let res = init(&mut deps, env, init_msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        // WE RECORD THE SCORE
+        let _env = mock_env("oracle", &coins(20, "token"));
+        let msg = HandleMsg::Record {
+            subject: HumanAddr("creator".to_string()),
+            score: 300,
+            band: ScoreBand::Poor,
+            description: String::from("Your SCRTSibyl score is FAIR, with a total of 581 points, which qualifies you for a loan of up to $5000 USD. SCRTSibyl computed your score accounting for your Plaid diamond 12.5% apr interest credit card credit card your total current balance of $44520 and your 9 different bank accounts. An error occurred during computation of the metrics: velocity, and your score was rounded down. Try again later or log in using a different account."),
+            components: None,
+            loan_ceiling: None,
+            attestation: None,
+        };
+        let record_res = handle(&mut deps, _env, msg).unwrap();
+        assert_eq!(0, record_res.messages.len());
+
+        // Create a viewing key
+        let __env = mock_env("creator", &coins(20, "token"));
+        let v_key_msg = HandleMsg::GenerateViewingKey {
+            entropy: "This is a string".to_string(),
+            name: None,
+            padding: Some(String::from("Good job dude")),
+        };
+
+        handle(&mut deps, __env, v_key_msg).unwrap(); //generates key: "api_key_j0y+6OGIPoHIcEEJw3WiM2695AzuNcBu/qjDwDPdwUQ="
+
+        // Query w Viewing key
+        let query_msg = QueryMsg::Read {
+            address: HumanAddr("creator".to_string()),
+            key: ("api_key_j0y+6OGIPoHIcEEJw3WiM2695AzuNcBu/qjDwDPdwUQ=".to_string()),
+        };
+
+        let res = query(&deps, query_msg).unwrap();
+        let value: ScoreResponse = from_binary(&res).unwrap();
+        assert_eq!(300, value.score.unwrap());
+        assert_eq!("Your SCRTSibyl score is FAIR, with a total of 581 points, which qualifies you for a loan of up to $5000 USD. SCRTSibyl computed your score accounting for your Plaid diamond 12.5% apr interest credit card credit card your total current balance of $44520 and your 9 different bank accounts. An error occurred during computation of the metrics: velocity, and your score was rounded down. Try again later or log in using a different account.", value.description);
+    }
+
+    #[test]
+    fn record_requires_oracle() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let init_msg = InitMsg {
+            oracles: None,
+            ..default_init_msg()
+        };
+        let env = mock_env("creator", &coins(20, "token"));
+        init(&mut deps, env, init_msg).unwrap();
+
+        // A user cannot self-report a score
+        let env = mock_env("borrower", &coins(20, "token"));
+        let msg = HandleMsg::Record {
+            subject: HumanAddr("borrower".to_string()),
+            score: 900,
+            band: ScoreBand::Excellent,
+            description: "Self reported".to_string(),
+            components: None,
+            loan_ceiling: None,
+            attestation: None,
+        };
+        let res = handle(&mut deps, env, msg.clone());
+        assert_eq!(res.unwrap_err(), StdError::unauthorized());
+
+        // Only the admin can register an oracle
+        let env = mock_env("borrower", &coins(20, "token"));
+        let add_msg = HandleMsg::AddOracle {
+            address: HumanAddr("oracle".to_string()),
+            padding: None,
+        };
+        let res = handle(&mut deps, env, add_msg.clone());
+        assert_eq!(res.unwrap_err(), StdError::unauthorized());
+
+        let env = mock_env("creator", &coins(20, "token"));
+        handle(&mut deps, env, add_msg).unwrap();
+
+        let env = mock_env("oracle", &coins(20, "token"));
+        handle(&mut deps, env, msg.clone()).unwrap();
+
+        // A removed oracle can no longer record
+        let env = mock_env("creator", &coins(20, "token"));
+        let remove_msg = HandleMsg::RemoveOracle {
+            address: HumanAddr("oracle".to_string()),
+            padding: None,
+        };
+        handle(&mut deps, env, remove_msg).unwrap();
+
+        let env = mock_env("oracle", &coins(20, "token"));
+        let res = handle(&mut deps, env, msg);
+        assert_eq!(res.unwrap_err(), StdError::unauthorized());
+    }
+
+    #[test]
+    fn admin_handover() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let init_msg = InitMsg {
+            admin: Some(HumanAddr("admin".to_string())),
+            oracles: None,
+            ..default_init_msg()
+        };
+        let env = mock_env("creator", &coins(20, "token"));
+        init(&mut deps, env, init_msg).unwrap();
+
+        let add_msg = HandleMsg::AddOracle {
+            address: HumanAddr("oracle".to_string()),
+            padding: None,
+        };
+
+        // The deployer is not the admin when one is given at init
+        let env = mock_env("creator", &coins(20, "token"));
+        let res = handle(&mut deps, env, add_msg.clone());
+        assert_eq!(res.unwrap_err(), StdError::unauthorized());
+
+        let env = mock_env("admin", &coins(20, "token"));
+        let change_msg = HandleMsg::ChangeAdmin {
+            address: HumanAddr("new_admin".to_string()),
+            padding: None,
+        };
+        handle(&mut deps, env, change_msg).unwrap();
+
+        // Only the nominee can accept
+        let env = mock_env("creator", &coins(20, "token"));
+        let res = handle(&mut deps, env, HandleMsg::AcceptAdmin { padding: None });
+        assert_eq!(res.unwrap_err(), StdError::unauthorized());
+
+        // The old admin stays in charge until the handover is accepted
+        let env = mock_env("admin", &coins(20, "token"));
+        handle(&mut deps, env, add_msg.clone()).unwrap();
+
+        let env = mock_env("new_admin", &coins(20, "token"));
+        handle(&mut deps, env, HandleMsg::AcceptAdmin { padding: None }).unwrap();
+
+        let env = mock_env("admin", &coins(20, "token"));
+        let res = handle(&mut deps, env, add_msg.clone());
+        assert_eq!(res.unwrap_err(), StdError::unauthorized());
+
+        let env = mock_env("new_admin", &coins(20, "token"));
+        handle(&mut deps, env, add_msg).unwrap();
+    }
+
+    #[test]
+    fn record_enforces_max_size() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let init_msg = InitMsg {
+            max_size: 10,
+            ..default_init_msg()
+        };
+        let env = mock_env("creator", &coins(20, "token"));
+        init(&mut deps, env, init_msg).unwrap();
+
+        let msg = HandleMsg::Record {
+            subject: HumanAddr("borrower".to_string()),
+            score: 600,
+            band: ScoreBand::Good,
+            description: "This describes your score".to_string(),
+            components: None,
+            loan_ceiling: None,
+            attestation: None,
+        };
+        let env = mock_env("oracle", &coins(20, "token"));
+        let res = handle(&mut deps, env, msg.clone());
+        match res.unwrap_err() {
+            StdError::GenericErr { msg, .. } => assert_eq!(
+                "description_too_long: Description is 25 bytes long, the maximum allowed is 10 bytes",
+                msg
+            ),
+            err => panic!("Unexpected error: {:?}", err),
+        }
+
+        // The admin raises the limit
+        let env = mock_env("creator", &coins(20, "token"));
+        let set_msg = HandleMsg::SetMaxSize {
+            max_size: 100,
+            padding: None,
+        };
+        handle(&mut deps, env, set_msg).unwrap();
+
+        let env = mock_env("oracle", &coins(20, "token"));
+        handle(&mut deps, env, msg).unwrap();
+
+        let stats_res = query(&deps, QueryMsg::GetStats {}).unwrap();
+        let value: StatsResponse = from_binary(&stats_res).unwrap();
+        assert_eq!(100, value.max_size);
+        assert_eq!(1, value.score_count);
+
+        let env = mock_env("creator", &coins(20, "token"));
+        let set_msg = HandleMsg::SetMaxSize {
+            max_size: 0,
+            padding: None,
+        };
+        let res = handle(&mut deps, env, set_msg);
+        assert_eq!(res.unwrap_err(), ContractError::InvalidMaxSize.into());
+    }
+
+    #[test]
+    fn record_keeps_history() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let init_msg = default_init_msg();
+        let env = mock_env("creator", &coins(20, "token"));
+        init(&mut deps, env, init_msg).unwrap();
+
+        for score in &[500, 550, 600] {
+            let env = mock_env("oracle", &coins(20, "token"));
+            let msg = HandleMsg::Record {
+                subject: HumanAddr("borrower".to_string()),
+                score: *score,
+                band: ScoreBand::from_score(*score),
+                description: format!("Score of {}", score),
+                components: None,
+                loan_ceiling: None,
+                attestation: None,
+            };
+            handle(&mut deps, env, msg).unwrap();
+        }
+
+        let env = mock_env("borrower", &coins(20, "token"));
+        let v_key_msg = HandleMsg::GenerateViewingKey {
+            entropy: "This is a string".to_string(),
+            name: None,
+            padding: None,
+        };
+        let res = handle(&mut deps, env, v_key_msg).unwrap();
+        let key = match from_binary(&res.data.unwrap()).unwrap() {
+            HandleAnswer::GenerateViewingKey { key } => key,
+            _ => panic!("Unexpected answer"),
+        };
+
+        // The latest score is still the one returned by Read
+        let query_msg = QueryMsg::Read {
+            address: HumanAddr("borrower".to_string()),
+            key: key.to_string(),
+        };
+        let value: ScoreResponse = from_binary(&query(&deps, query_msg).unwrap()).unwrap();
+        assert_eq!(Some(600), value.score);
+
+        let query_msg = QueryMsg::ReadHistory {
+            address: HumanAddr("borrower".to_string()),
+            key: key.to_string(),
+            page: None,
+            page_size: 2,
+        };
+        let value: HistoryResponse = from_binary(&query(&deps, query_msg).unwrap()).unwrap();
+        assert_eq!(3, value.total);
+        let scores: Vec<u64> = value.entries.iter().map(|e| e.score).collect();
+        assert_eq!(vec![600, 550], scores);
+        assert_eq!(HumanAddr("oracle".to_string()), value.entries[0].oracle);
+
+        let query_msg = QueryMsg::ReadHistory {
+            address: HumanAddr("borrower".to_string()),
+            key: key.to_string(),
+            page: Some(1),
+            page_size: 2,
+        };
+        let value: HistoryResponse = from_binary(&query(&deps, query_msg).unwrap()).unwrap();
+        let scores: Vec<u64> = value.entries.iter().map(|e| e.score).collect();
+        assert_eq!(vec![500], scores);
+
+        // pages far past the end are empty rather than overflowing
+        let query_msg = QueryMsg::ReadHistory {
+            address: HumanAddr("borrower".to_string()),
+            key: key.to_string(),
+            page: Some(u32::MAX),
+            page_size: 2,
+        };
+        let value: HistoryResponse = from_binary(&query(&deps, query_msg).unwrap()).unwrap();
+        assert!(value.entries.is_empty());
+    }
+
+    #[test]
+    fn grantee_reads() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let init_msg = default_init_msg();
+        let env = mock_env("creator", &coins(20, "token"));
+        init(&mut deps, env, init_msg).unwrap();
+
+        let env = mock_env("oracle", &coins(20, "token"));
+        let msg = HandleMsg::Record {
+            subject: HumanAddr("borrower".to_string()),
+            score: 700,
+            band: ScoreBand::VeryGood,
+            description: "This describes your score".to_string(),
+            components: None,
+            loan_ceiling: None,
+            attestation: None,
+        };
+        handle(&mut deps, env, msg).unwrap();
+
+        let env = mock_env("lender", &coins(20, "token"));
+        let v_key_msg = HandleMsg::GenerateViewingKey {
+            entropy: "This is a string".to_string(),
+            name: None,
+            padding: None,
+        };
+        let res = handle(&mut deps, env, v_key_msg).unwrap();
+        let key = match from_binary(&res.data.unwrap()).unwrap() {
+            HandleAnswer::GenerateViewingKey { key } => key,
+            _ => panic!("Unexpected answer"),
+        };
+        let query_msg = QueryMsg::ReadAsGrantee {
+            owner: HumanAddr("borrower".to_string()),
+            grantee: HumanAddr("lender".to_string()),
+            key: key.to_string(),
+        };
+
+        // No grant yet
+        let res = query(&deps, query_msg.clone());
+        assert_eq!(res.unwrap_err(), StdError::unauthorized());
+
+        let env = mock_env("borrower", &coins(20, "token"));
+        let grant_msg = HandleMsg::GrantAccess {
+            grantee: HumanAddr("lender".to_string()),
+            expires_at: None,
+            max_reads: None,
+            padding: None,
+        };
+        handle(&mut deps, env.clone(), grant_msg).unwrap();
+
+        // queries judge freshness at the latest transaction to the contract
+        let value: ScoreResponse = from_binary(&query(&deps, query_msg.clone()).unwrap()).unwrap();
+        assert_eq!(Some(700), value.score);
+        assert_eq!(env.block.time, value.as_of);
+
+        // A capped grant can only be used through the handle message
+        let env = mock_env("borrower", &coins(20, "token"));
+        let grant_msg = HandleMsg::GrantAccess {
+            grantee: HumanAddr("lender".to_string()),
+            expires_at: None,
+            max_reads: Some(1),
+            padding: None,
+        };
+        handle(&mut deps, env, grant_msg).unwrap();
+
+        let res = query(&deps, query_msg.clone());
+        assert_eq!(res.unwrap_err(), ContractError::GrantReadLimited.into());
+
+        let read_msg = HandleMsg::ReadAsGrantee {
+            owner: HumanAddr("borrower".to_string()),
+            padding: None,
+        };
+        let env = mock_env("lender", &coins(20, "token"));
+        let res = handle(&mut deps, env, read_msg.clone()).unwrap();
+        match from_binary(&res.data.unwrap()).unwrap() {
+            HandleAnswer::ReadAsGrantee { data } => assert_eq!(Some(700), data.score),
+            _ => panic!("Unexpected answer"),
+        }
+
+        let env = mock_env("lender", &coins(20, "token"));
+        let res = handle(&mut deps, env, read_msg.clone());
+        assert_eq!(res.unwrap_err(), ContractError::GrantExhausted.into());
+
+        // Expired grants are refused
+        let mut env = mock_env("borrower", &coins(20, "token"));
+        let expires_at = env.block.time + 100;
+        let grant_msg = HandleMsg::GrantAccess {
+            grantee: HumanAddr("lender".to_string()),
+            expires_at: Some(expires_at),
+            max_reads: None,
+            padding: None,
+        };
+        handle(&mut deps, env.clone(), grant_msg).unwrap();
+        query(&deps, query_msg.clone()).unwrap();
+
+        env.message.sender = HumanAddr("lender".to_string());
+        env.block.time = expires_at;
+        let res = handle(&mut deps, env, read_msg);
+        assert_eq!(res.unwrap_err(), ContractError::GrantExpired.into());
+        let res = query(&deps, query_msg.clone());
+        assert_eq!(res.unwrap_err(), ContractError::GrantExpired.into());
+
+        // Revoked grants are gone
+        let env = mock_env("borrower", &coins(20, "token"));
+        let revoke_msg = HandleMsg::RevokeAccess {
+            grantee: HumanAddr("lender".to_string()),
+            padding: None,
+        };
+        handle(&mut deps, env, revoke_msg).unwrap();
+        let res = query(&deps, query_msg);
+        assert_eq!(res.unwrap_err(), StdError::unauthorized());
+    }
+
+    #[test]
+    fn stale_scores() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let init_msg = InitMsg {
+            score_validity: Some(1000),
+            ..default_init_msg()
+        };
+        let env = mock_env("creator", &coins(20, "token"));
+        init(&mut deps, env, init_msg).unwrap();
+
+        let mut env = mock_env("oracle", &coins(20, "token"));
+        let recorded_at = env.block.time;
+        let msg = HandleMsg::Record {
+            subject: HumanAddr("borrower".to_string()),
+            score: 700,
+            band: ScoreBand::VeryGood,
+            description: "This describes your score".to_string(),
+            components: None,
+            loan_ceiling: None,
+            attestation: None,
+        };
+        handle(&mut deps, env.clone(), msg).unwrap();
+
+        env.message.sender = HumanAddr("borrower".to_string());
+        let grant_msg = HandleMsg::GrantAccess {
+            grantee: HumanAddr("lender".to_string()),
+            expires_at: None,
+            max_reads: None,
+            padding: None,
+        };
+        handle(&mut deps, env.clone(), grant_msg).unwrap();
+
+        let value = query_read(&deps, &HumanAddr("borrower".to_string()), recorded_at).unwrap();
+        assert_eq!(Some(recorded_at + 1000), value.expires_at);
+        assert!(!value.is_stale);
+
+        // Move past the validity window
+        env.message.sender = HumanAddr("lender".to_string());
+        env.block.time = recorded_at + 1000;
+        let read_msg = HandleMsg::ReadAsGrantee {
+            owner: HumanAddr("borrower".to_string()),
+            padding: None,
+        };
+        let res = handle(&mut deps, env, read_msg);
+        assert_eq!(res.unwrap_err(), ContractError::ScoreExpired.into());
+
+        // The owner still sees the score, flagged as stale
+        let value = query_read(
+            &deps,
+            &HumanAddr("borrower".to_string()),
+            recorded_at + 1000,
+        )
+        .unwrap();
+        assert_eq!(Some(700), value.score);
+        assert!(value.is_stale);
+
+        // Extending the window makes the score fresh again
+        let env = mock_env("creator", &coins(20, "token"));
+        let set_msg = HandleMsg::SetScoreValidity {
+            score_validity: 2000,
+            padding: None,
+        };
+        handle(&mut deps, env, set_msg).unwrap();
+        let value = query_read(
+            &deps,
+            &HumanAddr("borrower".to_string()),
+            recorded_at + 1000,
+        )
+        .unwrap();
+        assert!(!value.is_stale);
+
+        let stats_res = query(&deps, QueryMsg::GetStats {}).unwrap();
+        let value: StatsResponse = from_binary(&stats_res).unwrap();
+        assert_eq!(2000, value.score_validity);
+    }
+
+    #[test]
+    fn record_structured_score() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let init_msg = default_init_msg();
+        let env = mock_env("creator", &coins(20, "token"));
+        init(&mut deps, env, init_msg).unwrap();
+
+        let components = vec![
+            ScoreComponent {
+                name: "credit_mix".to_string(),
+                score: 80,
+                weight: 60,
+            },
+            ScoreComponent {
+                name: "velocity".to_string(),
+                score: 40,
+                weight: 50,
+            },
+        ];
+        let env = mock_env("oracle", &coins(20, "token"));
+        let msg = HandleMsg::Record {
+            subject: HumanAddr("borrower".to_string()),
+            score: 581,
+            band: ScoreBand::Fair,
+            description: "This describes your score".to_string(),
+            components: Some(components.clone()),
+            loan_ceiling: Some(5000),
+            attestation: None,
+        };
+        let res = handle(&mut deps, env, msg);
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::InvalidComponents { total_weight: 110 }.into()
+        );
+
+        let mut components = components;
+        components[1].weight = 40;
+        let env = mock_env("oracle", &coins(20, "token"));
+        let msg = HandleMsg::Record {
+            subject: HumanAddr("borrower".to_string()),
+            score: 581,
+            band: ScoreBand::Fair,
+            description: "This describes your score".to_string(),
+            components: Some(components.clone()),
+            loan_ceiling: Some(5000),
+            attestation: None,
+        };
+        handle(&mut deps, env.clone(), msg).unwrap();
+
+        // the band is the oracle's, not one the contract derives from the score
+        let value = query_read(&deps, &HumanAddr("borrower".to_string()), env.block.time).unwrap();
+        assert_eq!(Some(ScoreBand::Fair), value.band);
+        assert_eq!(Some(components), value.components);
+        assert_eq!(Some(5000), value.loan_ceiling);
+    }
+
+    #[test]
+    fn migrate_legacy_records() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let init_msg = InitMsg {
+            k_anonymity: Some(2),
+            ..default_init_msg()
+        };
+        let env = mock_env("creator", &coins(20, "token"));
+        init(&mut deps, env, init_msg).unwrap();
+
+        let version = ReadonlyConfig::from_storage(&deps.storage)
+            .contract_version()
+            .unwrap();
+        assert_eq!(CONTRACT_NAME, version.name);
+        assert_eq!(CONTRACT_VERSION, version.version);
+
+        // Three records in the untagged layout written at the root of the storage before
+        // versioning, one current record
+        for name in &["legacy_1", "legacy_2", "legacy_3"] {
+            let address = deps
+                .api
+                .canonical_address(&HumanAddr(name.to_string()))
+                .unwrap();
+            // the oracle used to name the band only in the description, if at all
+            let description = match *name {
+                "legacy_1" => "Your SCRTSibyl score is FAIR, with a total of 620 points",
+                _ => "Legacy score",
+            };
+            let legacy = UserV1 {
+                score: 620,
+                timestamp: 1,
+                description: description.as_bytes().to_vec(),
+            };
+            save(&mut deps.storage, address.as_slice(), &legacy).unwrap();
+        }
+        let env = mock_env("oracle", &coins(20, "token"));
+        let msg = HandleMsg::Record {
+            subject: HumanAddr("borrower".to_string()),
+            score: 700,
+            band: ScoreBand::VeryGood,
+            description: "This describes your score".to_string(),
+            components: None,
+            loan_ceiling: None,
+            attestation: None,
+        };
+        handle(&mut deps, env, msg).unwrap();
+
+        // Legacy records are readable before they are migrated, but not counted yet
+        let value = query_read(&deps, &HumanAddr("legacy_1".to_string()), 1).unwrap();
+        assert_eq!(Some(620), value.score);
+        assert_eq!(Some(ScoreBand::Fair), value.band);
+        let stats: StatsResponse =
+            from_binary(&query(&deps, QueryMsg::GetStats {}).unwrap()).unwrap();
+        assert_eq!(1, stats.score_count);
+
+        let migrate_msg = |names: &[&str]| HandleMsg::MigrateStorage {
+            addresses: names
+                .iter()
+                .map(|name| HumanAddr(name.to_string()))
+                .collect(),
+            legacy_prng_seed: None,
+            padding: None,
+        };
+        let env = mock_env("oracle", &coins(20, "token"));
+        let res = handle(&mut deps, env.clone(), migrate_msg(&["legacy_1"]));
+        assert_eq!(res.unwrap_err(), StdError::unauthorized());
+
+        // a legacy record rewritten before it is migrated starts being counted
+        let msg = HandleMsg::Record {
+            subject: HumanAddr("legacy_3".to_string()),
+            score: 580,
+            band: ScoreBand::Good,
+            description: "This describes your score".to_string(),
+            components: None,
+            loan_ceiling: None,
+            attestation: None,
+        };
+        handle(&mut deps, env, msg).unwrap();
+        let stats: StatsResponse =
+            from_binary(&query(&deps, QueryMsg::GetStats {}).unwrap()).unwrap();
+        assert_eq!(2, stats.score_count);
+        assert_eq!(Some(640), stats.mean_score);
+
+        // addresses without a legacy record are skipped
+        let env = mock_env("creator", &coins(20, "token"));
+        let res = handle(
+            &mut deps,
+            env.clone(),
+            migrate_msg(&["legacy_1", "borrower", "nobody"]),
+        )
+        .unwrap();
+        match from_binary(&res.data.unwrap()).unwrap() {
+            HandleAnswer::MigrateStorage { migrated } => assert_eq!(1, migrated),
+            _ => panic!("Unexpected answer"),
+        }
+        let res = handle(
+            &mut deps,
+            env,
+            migrate_msg(&["legacy_1", "legacy_2", "legacy_3"]),
+        )
+        .unwrap();
+        match from_binary(&res.data.unwrap()).unwrap() {
+            HandleAnswer::MigrateStorage { migrated } => assert_eq!(1, migrated),
+            _ => panic!("Unexpected answer"),
+        }
+
+        let address = deps
+            .api
+            .canonical_address(&HumanAddr("legacy_2".to_string()))
+            .unwrap();
+        assert!(!migrate_user(&mut deps.storage, &address).unwrap());
+        assert!(deps.storage.get(address.as_slice()).is_none());
+        let stats: StatsResponse =
+            from_binary(&query(&deps, QueryMsg::GetStats {}).unwrap()).unwrap();
+        assert_eq!(4, stats.score_count);
+        assert_eq!(Some(630), stats.mean_score);
+        let value = query_read(&deps, &HumanAddr("legacy_2".to_string()), 1).unwrap();
+        assert_eq!(Some(620), value.score);
+        assert_eq!(Some(ScoreBand::Good), value.band);
+    }
+
+    #[test]
+    fn migrate_legacy_state() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        // the state as the unversioned contract's `init` stored it
+        let legacy = StateV1 {
+            max_size: 10000,
+            score_count: 3,
+            prng_seed: sha_256(base64::encode("deployer seed").as_bytes()).to_vec(),
+        };
+        save(&mut deps.storage, CONFIG_KEY, &legacy).unwrap();
+        assert!(load_state(&deps.storage).is_err());
+
+        let migrate_msg = |seed: Option<&str>| HandleMsg::MigrateStorage {
+            addresses: vec![],
+            legacy_prng_seed: seed.map(String::from),
+            padding: None,
+        };
+        let env = mock_env("attacker", &coins(20, "token"));
+        let res = handle(&mut deps, env.clone(), migrate_msg(None));
+        assert_eq!(res.unwrap_err(), StdError::unauthorized());
+        let res = handle(&mut deps, env, migrate_msg(Some("guessed seed")));
+        assert_eq!(res.unwrap_err(), StdError::unauthorized());
+
+        let env = mock_env("deployer", &coins(20, "token"));
+        handle(&mut deps, env, migrate_msg(Some("deployer seed"))).unwrap();
+        let state = load_state(&deps.storage).unwrap();
+        assert_eq!(HumanAddr("deployer".to_string()), state.admin);
+        assert_eq!(10000, state.max_size);
+        assert_eq!(legacy.prng_seed, state.prng_seed);
+        assert_eq!(DEFAULT_SCORE_VALIDITY, state.score_validity);
+
+        // once claimed, the seed no longer grants anything
+        let env = mock_env("attacker", &coins(20, "token"));
+        let res = handle(&mut deps, env, migrate_msg(Some("deployer seed")));
+        assert_eq!(res.unwrap_err(), StdError::unauthorized());
+
+        // the deployer now runs the contract as its admin
+        let env = mock_env("deployer", &coins(20, "token"));
+        let msg = HandleMsg::AddOracle {
+            address: HumanAddr("oracle".to_string()),
+            padding: None,
+        };
+        handle(&mut deps, env, msg).unwrap();
+    }
+
+    #[test]
+    fn record_enforces_score_range() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let init_msg = default_init_msg();
+        let env = mock_env("creator", &coins(20, "token"));
+        init(&mut deps, env, init_msg).unwrap();
+
+        let record = |score: u64| HandleMsg::Record {
+            subject: HumanAddr("borrower".to_string()),
+            score,
+            band: ScoreBand::from_score(score),
+            description: "This describes your score".to_string(),
+            components: None,
+            loan_ceiling: None,
+            attestation: None,
+        };
+
+        let env = mock_env("oracle", &coins(20, "token"));
+        let res = handle(&mut deps, env, record(u64::MAX));
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::ScoreOutOfRange {
+                score: u64::MAX,
+                min_score: 300,
+                max_score: 900
+            }
+            .into()
+        );
+        let env = mock_env("oracle", &coins(20, "token"));
+        handle(&mut deps, env, record(900)).unwrap();
+
+        let env = mock_env("creator", &coins(20, "token"));
+        let set_msg = HandleMsg::SetScoreRange {
+            min_score: 900,
+            max_score: 100,
+            padding: None,
+        };
+        let res = handle(&mut deps, env, set_msg);
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::InvalidScoreRange {
+                min_score: 900,
+                max_score: 100
+            }
+            .into()
+        );
+
+        let env = mock_env("creator", &coins(20, "token"));
+        let set_msg = HandleMsg::SetScoreRange {
+            min_score: 0,
+            max_score: 1000,
+            padding: None,
+        };
+        handle(&mut deps, env, set_msg).unwrap();
+
+        let env = mock_env("oracle", &coins(20, "token"));
+        handle(&mut deps, env, record(1000)).unwrap();
+
+        let stats_res = query(&deps, QueryMsg::GetStats {}).unwrap();
+        let value: StatsResponse = from_binary(&stats_res).unwrap();
+        assert_eq!(0, value.min_score);
+        assert_eq!(1000, value.max_score);
+    }
+
+    #[test]
+    fn record_attested_score() {
+        use secret_toolkit::crypto::secp256k1::PrivateKey;
+
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let init_msg = InitMsg {
+            oracles: None,
+            ..default_init_msg()
+        };
+        let env = mock_env("creator", &coins(20, "token"));
+        init(&mut deps, env, init_msg).unwrap();
+
+        let signing_key = PrivateKey::parse(&[1u8; 32]).unwrap();
+        let pubkey = Binary::from(&signing_key.pubkey().serialize_compressed()[..]);
+        let env = mock_env("creator", &coins(20, "token"));
+        let add_msg = HandleMsg::AddOracleKey {
+            oracle: HumanAddr("oracle".to_string()),
+            pubkey: pubkey.clone(),
+            padding: None,
+        };
+        handle(&mut deps, env, add_msg).unwrap();
+
+        let env = mock_env("borrower", &coins(20, "token"));
+        let now = env.block.time;
+        let attest = |contract: &HumanAddr, timestamp: u64, nonce: u64| {
+            let payload = AttestationPayload {
+                chain_id: env.block.chain_id.clone(),
+                contract: contract.clone(),
+                subject: HumanAddr("borrower".to_string()),
+                score: 700,
+                band: ScoreBand::VeryGood,
+                description: "This describes your score".to_string(),
+                components: vec![],
+                loan_ceiling: None,
+                timestamp,
+                nonce,
+            };
+            let signature = signing_key.sign(&sha_256(to_binary(&payload).unwrap().as_slice()));
+            ScoreAttestation {
+                pubkey: pubkey.clone(),
+                timestamp,
+                nonce,
+                signature: Binary::from(&signature.serialize()[..]),
+            }
+        };
+        let attested = |description: &str, attestation: ScoreAttestation| HandleMsg::Record {
+            subject: HumanAddr("borrower".to_string()),
+            score: 700,
+            band: ScoreBand::VeryGood,
+            description: description.to_string(),
+            components: None,
+            loan_ceiling: None,
+            attestation: Some(attestation),
+        };
+        let record =
+            |description: &str| attested(description, attest(&env.contract.address, now - 60, 1));
+
+        // an attestation signed for another deployment is rejected
+        let other_contract = HumanAddr("other_contract".to_string());
+        let res = handle(
+            &mut deps,
+            env.clone(),
+            attested(
+                "This describes your score",
+                attest(&other_contract, now - 60, 1),
+            ),
+        );
+        assert!(res
+            .unwrap_err()
+            .to_string()
+            .contains("attestation_invalid: Invalid score attestation"));
+
+        // a signature over a different description is rejected
+        let res = handle(&mut deps, env.clone(), record("A better description"));
+        assert!(res
+            .unwrap_err()
+            .to_string()
+            .contains("attestation_invalid: Invalid score attestation"));
+
+        // so is a genuine score submitted with a better band
+        let msg = HandleMsg::Record {
+            subject: HumanAddr("borrower".to_string()),
+            score: 700,
+            band: ScoreBand::Excellent,
+            description: "This describes your score".to_string(),
+            components: None,
+            loan_ceiling: None,
+            attestation: Some(attest(&env.contract.address, now - 60, 1)),
+        };
+        let res = handle(&mut deps, env.clone(), msg);
+        assert!(res
+            .unwrap_err()
+            .to_string()
+            .contains("attestation_invalid: Invalid score attestation"));
+
+        handle(&mut deps, env.clone(), record("This describes your score")).unwrap();
+        let value = query_read(&deps, &HumanAddr("borrower".to_string()), now).unwrap();
+        assert_eq!(Some(700), value.score);
+        assert_eq!(Some(now - 60), value.timestamp);
+        let (history, _) = get_score_history(
+            &deps.storage,
+            &deps
+                .api
+                .canonical_address(&HumanAddr("borrower".to_string()))
+                .unwrap(),
+            0,
+            10,
+        )
+        .unwrap();
+        assert_eq!(HumanAddr("oracle".to_string()), history[0].oracle);
+
+        let res = handle(&mut deps, env.clone(), record("This describes your score"));
+        assert_eq!(res.unwrap_err(), ContractError::AttestationOutdated.into());
+
+        // an older attestation can't replace a newer score, even with an unused nonce
+        let newer = attest(&env.contract.address, now - 30, 2);
+        handle(
+            &mut deps,
+            env.clone(),
+            attested("This describes your score", newer.clone()),
+        )
+        .unwrap();
+        let older = attest(&env.contract.address, now - 90, 3);
+        let res = handle(
+            &mut deps,
+            env.clone(),
+            attested("This describes your score", older),
+        );
+        assert_eq!(res.unwrap_err(), ContractError::AttestationOutdated.into());
+        let reused = attest(&env.contract.address, now - 20, 2);
+        let res = handle(
+            &mut deps,
+            env.clone(),
+            attested("This describes your score", reused),
+        );
+        assert_eq!(res.unwrap_err(), ContractError::AttestationReplayed.into());
+
+        let latest = attested(
+            "This describes your score",
+            attest(&env.contract.address, now - 10, 4),
+        );
+        let env = mock_env("creator", &coins(20, "token"));
+        let remove_msg = HandleMsg::RemoveOracleKey {
+            pubkey: pubkey.clone(),
+            padding: None,
+        };
+        handle(&mut deps, env, remove_msg).unwrap();
+        let env = mock_env("borrower", &coins(20, "token"));
+        let res = handle(&mut deps, env, latest);
+        assert_eq!(res.unwrap_err(), StdError::unauthorized());
+    }
+
+    #[test]
+    fn permit_permissions() {
+        use secret_toolkit::permit::{PermitParams, PermitSignature, PubKey};
+
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let init_msg = default_init_msg();
+        let env = mock_env("creator", &coins(20, "token"));
+        init(&mut deps, env, init_msg).unwrap();
+
+        let env = mock_env("oracle", &coins(20, "token"));
+        let msg = HandleMsg::Record {
+            subject: HumanAddr("borrower".to_string()),
+            score: 700,
+            band: ScoreBand::VeryGood,
+            description: "This describes your score".to_string(),
+            components: None,
+            loan_ceiling: Some(5000),
+            attestation: None,
+        };
+        handle(&mut deps, env, msg).unwrap();
+
+        let permit = Permit {
+            params: PermitParams {
+                allowed_tokens: vec![],
+                permit_name: "lender".to_string(),
+                chain_id: "secret-4".to_string(),
+                permissions: vec![Permission::Balance],
+            },
+            signature: PermitSignature {
+                pub_key: PubKey {
+                    r#type: "tendermint/PubKeySecp256k1".to_string(),
+                    value: Binary::default(),
+                },
+                signature: Binary::default(),
+            },
+        };
+        check_permission(&permit, Permission::Balance, "query score band").unwrap();
+        for (permission, action) in &[
+            (Permission::History, "query score"),
+            (Permission::Owner, "query score breakdown"),
+        ] {
+            let res = check_permission(&permit, permission.clone(), action);
+            assert_eq!(
+                res.unwrap_err(),
+                ContractError::PermissionDenied {
+                    action: action.to_string(),
+                    permissions: "[Balance]".to_string(),
+                }
+            );
+        }
+
+        // a balance permit shows the band, but neither the score nor what it is based on
+        let borrower = HumanAddr("borrower".to_string());
+        let value = band_only(query_read(&deps, &borrower, 1).unwrap());
+        assert_eq!(
+            BandResponse {
+                band: Some(ScoreBand::VeryGood),
+                timestamp: Some(1571797419),
+                expires_at: Some(1571797419 + DEFAULT_SCORE_VALIDITY),
+                is_stale: false,
+                as_of: 1,
+            },
+            value
+        );
+
+        // a history permit adds the raw score, but still not the breakdown
+        let value = without_breakdown(query_read(&deps, &borrower, 1).unwrap());
+        assert_eq!(Some(700), value.score);
+        assert_eq!("N/A", value.description);
+        assert_eq!(None, value.loan_ceiling);
+
+        let env = mock_env("borrower", &coins(20, "token"));
+        for grantee in &["lender", "other_lender"] {
+            let grant_msg = HandleMsg::GrantAccess {
+                grantee: HumanAddr(grantee.to_string()),
+                expires_at: None,
+                max_reads: Some(1),
+                padding: None,
+            };
+            handle(&mut deps, env.clone(), grant_msg).unwrap();
+        }
+        let revoke_msg = HandleMsg::RevokeAccess {
+            grantee: HumanAddr("lender".to_string()),
+            padding: None,
+        };
+        handle(&mut deps, env, revoke_msg).unwrap();
+
+        let value = query_grants(&deps, &borrower).unwrap();
+        assert_eq!(
+            vec![GrantInfo {
+                grantee: HumanAddr("other_lender".to_string()),
+                expires_at: None,
+                max_reads: Some(1),
+                reads: 0,
+            }],
+            value.grants
+        );
+    }
+
+    #[test]
+    fn named_viewing_keys() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let init_msg = default_init_msg();
+        let env = mock_env("creator", &coins(20, "token"));
+        init(&mut deps, env, init_msg).unwrap();
+
+        let env = mock_env("oracle", &coins(20, "token"));
+        let msg = HandleMsg::Record {
+            subject: HumanAddr("borrower".to_string()),
+            score: 700,
+            band: ScoreBand::VeryGood,
+            description: "This describes your score".to_string(),
+            components: None,
+            loan_ceiling: None,
+            attestation: None,
+        };
+        handle(&mut deps, env, msg).unwrap();
+
+        let env = mock_env("borrower", &coins(20, "token"));
+        for (key, name) in &[("my key", None), ("phone key", Some("phone"))] {
+            let set_msg = HandleMsg::SetViewingKey {
+                key: key.to_string(),
+                name: name.map(String::from),
+                padding: None,
+            };
+            handle(&mut deps, env.clone(), set_msg).unwrap();
+        }
+        let v_key_msg = HandleMsg::GenerateViewingKey {
+            entropy: "This is a string".to_string(),
+            name: Some("lender".to_string()),
+            padding: None,
+        };
+        let res = handle(&mut deps, env.clone(), v_key_msg).unwrap();
+        let lender_key = match from_binary(&res.data.unwrap()).unwrap() {
+            HandleAnswer::GenerateViewingKey { key } => key,
+            _ => panic!("Unexpected answer"),
+        };
+
+        // every key authenticates on its own
+        let read = |key: &str| QueryMsg::Read {
+            address: HumanAddr("borrower".to_string()),
+            key: key.to_string(),
+        };
+        for key in &["my key", "phone key", &lender_key.to_string()] {
+            let res = query(&deps, read(key)).unwrap();
+            let value: ScoreResponse = from_binary(&res).unwrap();
+            assert_eq!(Some(700), value.score);
+        }
+        assert_eq!(
+            query(&deps, read("wrong key")).unwrap_err(),
+            StdError::unauthorized()
+        );
+
+        let delete_msg = HandleMsg::DeleteViewingKey {
+            name: Some("phone".to_string()),
+            padding: None,
+        };
+        handle(&mut deps, env.clone(), delete_msg).unwrap();
+        assert_eq!(
+            query(&deps, read("phone key")).unwrap_err(),
+            StdError::unauthorized()
+        );
+        query(&deps, read("my key")).unwrap();
+
+        let list_msg = QueryMsg::ListViewingKeys {
+            address: HumanAddr("borrower".to_string()),
+            key: "my key".to_string(),
+        };
+        let value: ViewingKeysResponse = from_binary(&query(&deps, list_msg).unwrap()).unwrap();
+        assert!(value.has_unnamed);
+        assert_eq!(vec!["lender".to_string()], value.names);
+
+        for i in 1..MAX_NAMED_VIEWING_KEYS {
+            let set_msg = HandleMsg::SetViewingKey {
+                key: format!("key {}", i),
+                name: Some(format!("device {}", i)),
+                padding: None,
+            };
+            handle(&mut deps, env.clone(), set_msg).unwrap();
+        }
+        let set_msg = HandleMsg::SetViewingKey {
+            key: "one key too many".to_string(),
+            name: Some("laptop".to_string()),
+            padding: None,
+        };
+        let res = handle(&mut deps, env, set_msg);
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::TooManyViewingKeys {
+                max: MAX_NAMED_VIEWING_KEYS
+            }
+            .into()
+        );
+    }
+
+    #[test]
+    fn delete_my_score() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let init_msg = default_init_msg();
+        let env = mock_env("creator", &coins(20, "token"));
+        init(&mut deps, env, init_msg).unwrap();
+
+        for subject in &["borrower", "borrower", "other_borrower"] {
+            let env = mock_env("oracle", &coins(20, "token"));
+            let msg = HandleMsg::Record {
+                subject: HumanAddr(subject.to_string()),
+                score: 700,
+                band: ScoreBand::VeryGood,
+                description: "This describes your score".to_string(),
+                components: None,
+                loan_ceiling: None,
+                attestation: None,
+            };
+            handle(&mut deps, env, msg).unwrap();
+        }
+
+        let env = mock_env("borrower", &coins(20, "token"));
+        let set_msg = HandleMsg::SetViewingKey {
+            key: "my key".to_string(),
+            name: Some("phone".to_string()),
+            padding: None,
+        };
+        handle(&mut deps, env.clone(), set_msg).unwrap();
+        let grant_msg = HandleMsg::GrantAccess {
+            grantee: HumanAddr("lender".to_string()),
+            expires_at: None,
+            max_reads: None,
+            padding: None,
+        };
+        handle(&mut deps, env.clone(), grant_msg).unwrap();
+        let read_msg = HandleMsg::ReadAsGrantee {
+            owner: HumanAddr("borrower".to_string()),
+            padding: None,
+        };
+        handle(&mut deps, mock_env("lender", &[]), read_msg).unwrap();
+
+        let res = handle(
+            &mut deps,
+            env.clone(),
+            HandleMsg::DeleteMyScore { padding: None },
+        )
+        .unwrap();
+        match from_binary(&res.data.unwrap()).unwrap() {
+            HandleAnswer::DeleteMyScore { deleted, .. } => assert!(deleted),
+            _ => panic!("Unexpected answer"),
+        }
+
+        let borrower = HumanAddr("borrower".to_string());
+        let value = query_read(&deps, &borrower, 1).unwrap();
+        assert_eq!(None, value.score);
+        assert_eq!(0, query_history(&deps, &borrower, 0, 10).unwrap().total);
+        assert!(query_grants(&deps, &borrower).unwrap().grants.is_empty());
+        let owner = deps.api.canonical_address(&borrower).unwrap();
+        assert_eq!(
+            (vec![], 0),
+            get_access_log(&deps.storage, &owner, 0, 10).unwrap()
+        );
+        // no empty lists are left behind either
+        for prefix in &[PREFIX_VIEWING_KEY_NAMES, PREFIX_GRANTEES] {
+            let store = ReadonlyPrefixedStorage::new(prefix, &deps.storage);
+            assert_eq!(None, store.get(owner.as_slice()));
+        }
+        let read_msg = QueryMsg::Read {
+            address: borrower,
+            key: "my key".to_string(),
+        };
+        assert_eq!(
+            query(&deps, read_msg).unwrap_err(),
+            StdError::unauthorized()
+        );
+
+        // deleting twice doesn't count the user out twice
+        let res = handle(&mut deps, env, HandleMsg::DeleteMyScore { padding: None }).unwrap();
+        match from_binary(&res.data.unwrap()).unwrap() {
+            HandleAnswer::DeleteMyScore { deleted, .. } => assert!(!deleted),
+            _ => panic!("Unexpected answer"),
+        }
+        let value: StatsResponse =
+            from_binary(&query(&deps, QueryMsg::GetStats {}).unwrap()).unwrap();
+        assert_eq!(1, value.score_count);
+        let other_borrower = deps
+            .api
+            .canonical_address(&HumanAddr("other_borrower".to_string()))
+            .unwrap();
+        assert_eq!(
+            (vec![other_borrower], 2),
+            get_user_index(&deps.storage, 0, 10).unwrap()
+        );
+    }
+
+    #[test]
+    fn stats_are_k_anonymous() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let init_msg = InitMsg {
+            k_anonymity: Some(2),
+            ..default_init_msg()
+        };
+        let env = mock_env("creator", &coins(20, "token"));
+        init(&mut deps, env, init_msg).unwrap();
+
+        let record = |subject: &str, score: u64| HandleMsg::Record {
+            subject: HumanAddr(subject.to_string()),
+            score,
+            band: ScoreBand::from_score(score),
+            description: "This describes your score".to_string(),
+            components: None,
+            loan_ceiling: None,
+            attestation: None,
+        };
+        let stats = |deps: &Extern<_, _, _>| -> StatsResponse {
+            from_binary(&query(deps, QueryMsg::GetStats {}).unwrap()).unwrap()
+        };
+        let band_count = |stats: &StatsResponse, band: ScoreBand| {
+            stats.bands.iter().find(|b| b.band == band).unwrap().count
+        };
+
+        let env = mock_env("oracle", &coins(20, "token"));
+        handle(&mut deps, env.clone(), record("alice", 700)).unwrap();
+        let value = stats(&deps);
+        assert_eq!(None, value.mean_score);
+        assert_eq!(None, band_count(&value, ScoreBand::VeryGood));
+        assert_eq!(Some(0), band_count(&value, ScoreBand::Poor));
+        assert_eq!(None, value.fresh_count);
+
+        handle(&mut deps, env.clone(), record("bob", 720)).unwrap();
+        handle(&mut deps, env.clone(), record("carol", 400)).unwrap();
+        let value = stats(&deps);
+        assert_eq!(Some(610), value.mean_score);
+        // the larger band is withheld too, or it would give away the smaller one
+        assert_eq!(3, value.score_count);
+        assert_eq!(None, band_count(&value, ScoreBand::VeryGood));
+        assert_eq!(None, band_count(&value, ScoreBand::Poor));
+        assert_eq!(Some(3), value.fresh_count);
+        assert_eq!(Some(0), value.stale_count);
+
+        handle(&mut deps, env.clone(), record("dave", 420)).unwrap();
+        handle(&mut deps, env.clone(), record("erin", 410)).unwrap();
+        let value = stats(&deps);
+        assert_eq!(Some(530), value.mean_score);
+        assert_eq!(Some(2), band_count(&value, ScoreBand::VeryGood));
+        assert_eq!(Some(3), band_count(&value, ScoreBand::Poor));
+
+        // an updated score moves to its new band
+        handle(&mut deps, env, record("carol", 650)).unwrap();
+        let value = stats(&deps);
+        assert_eq!(Some(580), value.mean_score);
+        assert_eq!(Some(3), band_count(&value, ScoreBand::VeryGood));
+        assert_eq!(Some(2), band_count(&value, ScoreBand::Poor));
+
+        // and deleted ones leave the statistics
+        let env = mock_env("alice", &coins(20, "token"));
+        handle(&mut deps, env, HandleMsg::DeleteMyScore { padding: None }).unwrap();
+        let value = stats(&deps);
+        assert_eq!(Some(550), value.mean_score);
+        assert_eq!(Some(2), band_count(&value, ScoreBand::VeryGood));
+
+        // once the validity window has passed, all scores count as stale
+        let mut env = mock_env("creator", &coins(20, "token"));
+        env.block.time += DEFAULT_SCORE_VALIDITY + SECONDS_PER_DAY;
+        let set_msg = HandleMsg::SetKAnonymity {
+            k_anonymity: 1,
+            padding: None,
+        };
+        handle(&mut deps, env, set_msg).unwrap();
+        let value = stats(&deps);
+        assert_eq!(Some(0), value.fresh_count);
+        assert_eq!(Some(4), value.stale_count);
+    }
+
+    #[test]
+    fn read_percentile() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let init_msg = InitMsg {
+            k_anonymity: Some(4),
+            ..default_init_msg()
+        };
+        let env = mock_env("creator", &coins(20, "token"));
+        init(&mut deps, env, init_msg).unwrap();
+
+        let record = |subject: &str, score: u64| HandleMsg::Record {
+            subject: HumanAddr(subject.to_string()),
+            score,
+            band: ScoreBand::from_score(score),
+            description: "This describes your score".to_string(),
+            components: None,
+            loan_ceiling: None,
+            attestation: None,
+        };
+        let percentile = |deps: &Extern<_, _, _>, subject: &str| {
+            query_read(deps, &HumanAddr(subject.to_string()), 1)
+                .unwrap()
+                .percentile
+        };
+
+        let env = mock_env("oracle", &coins(20, "token"));
+        for (subject, score) in &[("user_a", 400), ("user_b", 500), ("user_c", 600)] {
+            handle(&mut deps, env.clone(), record(subject, *score)).unwrap();
+        }
+        // too few scores to rank anyone
+        assert_eq!(None, percentile(&deps, "user_a"));
+
+        handle(&mut deps, env.clone(), record("user_d", 800)).unwrap();
+        assert_eq!(Some(12), percentile(&deps, "user_a"));
+        assert_eq!(Some(62), percentile(&deps, "user_c"));
+        assert_eq!(Some(87), percentile(&deps, "user_d"));
+
+        // ranks follow updated scores
+        handle(&mut deps, env, record("user_a", 900)).unwrap();
+        assert_eq!(Some(87), percentile(&deps, "user_a"));
+        assert_eq!(Some(12), percentile(&deps, "user_b"));
+    }
+
+    #[test]
+    fn admin_lists_scores() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let init_msg = default_init_msg();
+        let env = mock_env("creator", &coins(20, "token"));
+        init(&mut deps, env, init_msg).unwrap();
+
+        let env = mock_env("oracle", &coins(20, "token"));
+        for (subject, score) in &[
+            ("borrower_1", 400),
+            ("borrower_2", 700),
+            ("borrower_3", 800),
+        ] {
+            let msg = HandleMsg::Record {
+                subject: HumanAddr(subject.to_string()),
+                score: *score,
+                band: ScoreBand::from_score(*score),
+                description: "This describes your score".to_string(),
+                components: None,
+                loan_ceiling: None,
+                attestation: None,
+            };
+            handle(&mut deps, env.clone(), msg).unwrap();
+        }
+
+        for address in &["creator", "borrower_1"] {
+            let env = mock_env(*address, &coins(20, "token"));
+            let set_msg = HandleMsg::SetViewingKey {
+                key: "key".to_string(),
+                name: None,
+                padding: None,
+            };
+            handle(&mut deps, env, set_msg).unwrap();
+        }
+        let list = |address: &str, start: Option<u32>| QueryMsg::ListScores {
+            address: HumanAddr(address.to_string()),
+            key: "key".to_string(),
+            start,
+            limit: Some(2),
+        };
+
+        let res = query(&deps, list("borrower_1", None));
+        assert_eq!(res.unwrap_err(), StdError::unauthorized());
+
+        let value: ListScoresResponse =
+            from_binary(&query(&deps, list("creator", None)).unwrap()).unwrap();
+        assert_eq!(3, value.total);
+        assert_eq!(
+            ScoreMetadata {
+                address: HumanAddr("borrower_1".to_string()),
+                timestamp: env.block.time,
+                oracle: Some(HumanAddr("oracle".to_string())),
+                band: ScoreBand::Poor,
+            },
+            value.scores[0]
+        );
+        assert_eq!(HumanAddr("borrower_2".to_string()), value.scores[1].address);
+        assert_eq!(Some(2), value.next);
+
+        // deleting scores between pages, the last one listed included, doesn't skip anyone
+        for address in &["borrower_1", "borrower_2"] {
+            let env = mock_env(*address, &coins(20, "token"));
+            handle(&mut deps, env, HandleMsg::DeleteMyScore { padding: None }).unwrap();
+        }
+        let value: ListScoresResponse =
+            from_binary(&query(&deps, list("creator", value.next)).unwrap()).unwrap();
+        assert_eq!(1, value.total);
+        assert_eq!(1, value.scores.len());
+        assert_eq!(HumanAddr("borrower_3".to_string()), value.scores[0].address);
+        assert_eq!(ScoreBand::Excellent, value.scores[0].band);
+        assert_eq!(None, value.next);
+
+        // and pages from the start leave out the deleted ones
+        let value: ListScoresResponse =
+            from_binary(&query(&deps, list("creator", None)).unwrap()).unwrap();
+        assert!(value.scores.is_empty());
+        assert_eq!(Some(2), value.next);
+    }
+
+    #[test]
+    fn contract_status() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let init_msg = default_init_msg();
+        let env = mock_env("creator", &coins(20, "token"));
+        init(&mut deps, env, init_msg).unwrap();
+
+        let record = HandleMsg::Record {
+            subject: HumanAddr("borrower".to_string()),
+            score: 700,
+            band: ScoreBand::VeryGood,
+            description: "This describes your score".to_string(),
+            components: None,
+            loan_ceiling: None,
+            attestation: None,
+        };
+        let set_status = |level: ContractStatus| HandleMsg::SetContractStatus {
+            level,
+            padding: None,
         };
+        let env = mock_env("oracle", &coins(20, "token"));
+        handle(&mut deps, env.clone(), record.clone()).unwrap();
+        let env = mock_env("borrower", &coins(20, "token"));
+        let set_msg = HandleMsg::SetViewingKey {
+            key: "key".to_string(),
+            name: None,
+            padding: None,
+        };
+        handle(&mut deps, env, set_msg).unwrap();
+
+        let env = mock_env("oracle", &coins(20, "token"));
+        let res = handle(&mut deps, env, set_status(ContractStatus::StopAll));
+        assert_eq!(res.unwrap_err(), StdError::unauthorized());
+
+        // writes are frozen, reads keep working
         let env = mock_env("creator", &coins(20, "token"));
-        let res = init(&mut deps, env, init_msg).unwrap();
-        assert_eq!(0, res.messages.len());
+        handle(&mut deps, env, set_status(ContractStatus::StopRecording)).unwrap();
+        let env = mock_env("oracle", &coins(20, "token"));
+        let res = handle(&mut deps, env, record.clone());
+        assert_eq!(res.unwrap_err(), ContractError::ContractStopped.into());
+        let read_msg = QueryMsg::Read {
+            address: HumanAddr("borrower".to_string()),
+            key: "key".to_string(),
+        };
+        query(&deps, read_msg.clone()).unwrap();
 
-        // WE RECORD THE SCORE
-        let _env = mock_env("creator", &coins(20, "token"));
+        // only safety actions remain once everything is stopped
+        let env = mock_env("creator", &coins(20, "token"));
+        handle(&mut deps, env, set_status(ContractStatus::StopAll)).unwrap();
+        assert_eq!(
+            query(&deps, read_msg.clone()).unwrap_err(),
+            ContractError::ContractStopped.into()
+        );
+        let env = mock_env("borrower", &coins(20, "token"));
+        let grant_msg = HandleMsg::GrantAccess {
+            grantee: HumanAddr("lender".to_string()),
+            expires_at: None,
+            max_reads: None,
+            padding: None,
+        };
+        let res = handle(&mut deps, env.clone(), grant_msg);
+        assert_eq!(res.unwrap_err(), ContractError::ContractStopped.into());
+        let revoke_msg = HandleMsg::RevokePermit {
+            permit_name: "lender".to_string(),
+            padding: None,
+        };
+        handle(&mut deps, env, revoke_msg).unwrap();
+        let value: StatsResponse =
+            from_binary(&query(&deps, QueryMsg::GetStats {}).unwrap()).unwrap();
+        assert_eq!(ContractStatus::StopAll, value.contract_status);
+
+        let env = mock_env("creator", &coins(20, "token"));
+        handle(&mut deps, env, set_status(ContractStatus::Normal)).unwrap();
+        let env = mock_env("oracle", &coins(20, "token"));
+        handle(&mut deps, env, record).unwrap();
+        query(&deps, read_msg).unwrap();
+    }
+
+    #[test]
+    fn score_requests() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let init_msg = default_init_msg();
+        let env = mock_env("creator", &coins(20, "token"));
+        init(&mut deps, env, init_msg).unwrap();
+
+        let env = mock_env("oracle", &coins(20, "token"));
         let msg = HandleMsg::Record {
-            score: 300,
+            subject: HumanAddr("borrower".to_string()),
+            score: 700,
+            band: ScoreBand::VeryGood,
             description: "This describes your score".to_string(),
+            components: None,
+            loan_ceiling: None,
+            attestation: None,
         };
-        let record_res = handle(&mut deps, _env, msg).unwrap();
-        assert_eq!(0, record_res.messages.len());
+        handle(&mut deps, env, msg).unwrap();
+
+        for lender in &["lender", "other_lender"] {
+            let env = mock_env(*lender, &coins(20, "token"));
+            let request_msg = HandleMsg::RequestScore {
+                borrower: HumanAddr("borrower".to_string()),
+                purpose: "Loan of $2000".to_string(),
+                expires_at: None,
+                padding: None,
+            };
+            handle(&mut deps, env, request_msg).unwrap();
+        }
+
+        let env = mock_env("borrower", &coins(20, "token"));
+        let set_msg = HandleMsg::SetViewingKey {
+            key: "key".to_string(),
+            name: None,
+            padding: None,
+        };
+        handle(&mut deps, env.clone(), set_msg).unwrap();
+        let pending_msg = QueryMsg::PendingRequests {
+            address: HumanAddr("borrower".to_string()),
+            key: "key".to_string(),
+        };
+        let value: PendingRequestsResponse =
+            from_binary(&query(&deps, pending_msg.clone()).unwrap()).unwrap();
+        assert_eq!(
+            ScoreRequestInfo {
+                lender: HumanAddr("lender".to_string()),
+                purpose: "Loan of $2000".to_string(),
+                expires_at: Some(env.block.time + MAX_REQUEST_LIFETIME),
+                requested_at: env.block.time,
+            },
+            value.requests[0]
+        );
+        assert_eq!(2, value.requests.len());
+
+        let approve_msg = HandleMsg::ApproveRequest {
+            lender: HumanAddr("lender".to_string()),
+            padding: None,
+        };
+        handle(&mut deps, env.clone(), approve_msg.clone()).unwrap();
+        let deny_msg = HandleMsg::DenyRequest {
+            lender: HumanAddr("other_lender".to_string()),
+            padding: None,
+        };
+        handle(&mut deps, env.clone(), deny_msg).unwrap();
+        let value: PendingRequestsResponse =
+            from_binary(&query(&deps, pending_msg.clone()).unwrap()).unwrap();
+        assert!(value.requests.is_empty());
+        let res = handle(&mut deps, env, approve_msg);
+        assert_eq!(res.unwrap_err(), ContractError::RequestNotFound.into());
+
+        // the approved lender gets exactly one read, the denied one none
+        let read_msg = HandleMsg::ReadAsGrantee {
+            owner: HumanAddr("borrower".to_string()),
+            padding: None,
+        };
+        let env = mock_env("lender", &coins(20, "token"));
+        handle(&mut deps, env.clone(), read_msg.clone()).unwrap();
+        let res = handle(&mut deps, env, read_msg.clone());
+        assert_eq!(res.unwrap_err(), ContractError::GrantExhausted.into());
+        let env = mock_env("other_lender", &coins(20, "token"));
+        let res = handle(&mut deps, env, read_msg.clone());
+        assert_eq!(res.unwrap_err(), StdError::unauthorized());
+
+        // approving a request adds to a grant the lender already holds instead of replacing it
+        let env = mock_env("borrower", &coins(20, "token"));
+        let grant_msg = HandleMsg::GrantAccess {
+            grantee: HumanAddr("other_lender".to_string()),
+            expires_at: None,
+            max_reads: None,
+            padding: None,
+        };
+        handle(&mut deps, env, grant_msg).unwrap();
+        let env = mock_env("other_lender", &coins(20, "token"));
+        let request_msg = HandleMsg::RequestScore {
+            borrower: HumanAddr("borrower".to_string()),
+            purpose: "Loan of $2000".to_string(),
+            expires_at: Some(u64::MAX),
+            padding: None,
+        };
+        handle(&mut deps, env.clone(), request_msg).unwrap();
+        let value: PendingRequestsResponse =
+            from_binary(&query(&deps, pending_msg.clone()).unwrap()).unwrap();
+        assert_eq!(
+            Some(env.block.time + MAX_REQUEST_LIFETIME),
+            value.requests[0].expires_at
+        );
+        let approve_msg = HandleMsg::ApproveRequest {
+            lender: HumanAddr("other_lender".to_string()),
+            padding: None,
+        };
+        handle(&mut deps, mock_env("borrower", &[]), approve_msg).unwrap();
+        for _ in 0..3 {
+            handle(&mut deps, env.clone(), read_msg.clone()).unwrap();
+        }
+
+        // a flood of requests pushes out the oldest ones instead of locking out new lenders
+        let request_msg = HandleMsg::RequestScore {
+            borrower: HumanAddr("borrower".to_string()),
+            purpose: "Loan of $2000".to_string(),
+            expires_at: None,
+            padding: None,
+        };
+        for i in 0..=MAX_PENDING_REQUESTS {
+            let env = mock_env(format!("spammer_{}", i), &[]);
+            handle(&mut deps, env, request_msg.clone()).unwrap();
+        }
+        handle(&mut deps, mock_env("lender", &[]), request_msg).unwrap();
+        let value: PendingRequestsResponse =
+            from_binary(&query(&deps, pending_msg).unwrap()).unwrap();
+        assert_eq!(MAX_PENDING_REQUESTS, value.requests.len());
+        assert_eq!(HumanAddr("spammer_2".to_string()), value.requests[0].lender);
+        assert_eq!(
+            HumanAddr("lender".to_string()),
+            value.requests[MAX_PENDING_REQUESTS - 1].lender
+        );
     }
 
     #[test]
-    fn stats_increment() {
-        // First we init
-        let mut deps = mock_dependencies(20, &coins(20, "token"));
-        let init_msg = InitMsg {
-            max_size: 10000,
-            prng_seed: "this is a padding".to_string(),
-        };
+    fn access_log() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let init_msg = default_init_msg();
         let env = mock_env("creator", &coins(20, "token"));
-        let res = init(&mut deps, env, init_msg).unwrap();
-        assert_eq!(0, res.messages.len());
+        init(&mut deps, env, init_msg).unwrap();
 
-        // WE RECORD THE SCORE
-        let _env = mock_env("submitter", &coins(20, "token"));
+        let env = mock_env("oracle", &coins(20, "token"));
         let msg = HandleMsg::Record {
-            score: 300,
+            subject: HumanAddr("borrower".to_string()),
+            score: 700,
+            band: ScoreBand::VeryGood,
             description: "This describes your score".to_string(),
+            components: None,
+            loan_ceiling: Some(5000),
+            attestation: None,
         };
+        handle(&mut deps, env, msg).unwrap();
 
-        handle(&mut deps, _env, msg).unwrap();
+        let env = mock_env("borrower", &coins(20, "token"));
+        let grant_msg = HandleMsg::GrantAccess {
+            grantee: HumanAddr("lender".to_string()),
+            expires_at: None,
+            max_reads: None,
+            padding: None,
+        };
+        handle(&mut deps, env.clone(), grant_msg).unwrap();
+        let set_msg = HandleMsg::SetViewingKey {
+            key: "key".to_string(),
+            name: None,
+            padding: None,
+        };
+        handle(&mut deps, env, set_msg).unwrap();
 
-        // WE QUERY THE STATS
-        let stats_msg = QueryMsg::GetStats {};
-        let stats_res = query(&deps, stats_msg).unwrap();
-        let value: StatsResponse = from_binary(&stats_res).unwrap();
-        assert_eq!(1, value.score_count);
+        let env = mock_env("lender", &coins(20, "token"));
+        let read_msg = HandleMsg::ReadScoreLogged {
+            owner: HumanAddr("borrower".to_string()),
+            fields: Some(vec![ScoreField::LoanCeiling]),
+            padding: None,
+        };
+        let res = handle(&mut deps, env.clone(), read_msg).unwrap();
+        match from_binary(&res.data.unwrap()).unwrap() {
+            HandleAnswer::ReadScoreLogged { data } => {
+                assert_eq!(None, data.score);
+                assert_eq!(None, data.band);
+                assert_eq!(Some(5000), data.loan_ceiling);
+            }
+            _ => panic!("unexpected answer"),
+        }
+        let read_msg = HandleMsg::ReadAsGrantee {
+            owner: HumanAddr("borrower".to_string()),
+            padding: None,
+        };
+        handle(&mut deps, env.clone(), read_msg).unwrap();
 
-        // WE RECORD THE SCORE A 2ND TIME WITH SAME USER
-        let __env = mock_env("submitter", &coins(20, "token"));
-        let msg = HandleMsg::Record {
-            score: 200,
-            description: "This describes your 2nd score".to_string(),
+        let log_msg = QueryMsg::AccessLog {
+            address: HumanAddr("borrower".to_string()),
+            key: "key".to_string(),
+            page: None,
+            page_size: 10,
         };
-        handle(&mut deps, __env, msg).unwrap();
+        let value: AccessLogResponse = from_binary(&query(&deps, log_msg).unwrap()).unwrap();
+        assert_eq!(2, value.total);
+        assert_eq!(HumanAddr("lender".to_string()), value.entries[0].reader);
+        assert_eq!(env.block.time, value.entries[0].timestamp);
+        assert_eq!(SCORE_FIELDS.to_vec(), value.entries[0].fields);
+        assert_eq!(vec![ScoreField::LoanCeiling], value.entries[1].fields);
 
-        // WE QUERY THE STATS again
-        let stats_msg2 = QueryMsg::GetStats {};
-        let stats_res2 = query(&deps, stats_msg2).unwrap();
-        let value2: StatsResponse = from_binary(&stats_res2).unwrap();
-        assert_eq!(1, value2.score_count);
+        // each entry's hash chains onto the one before it
+        let mut content = value.entries[1].hash.0.clone();
+        content.extend_from_slice(
+            deps.api
+                .canonical_address(&value.entries[0].reader)
+                .unwrap()
+                .as_slice(),
+        );
+        content.extend_from_slice(&env.block.time.to_be_bytes());
+        content.extend_from_slice(&Bincode2::serialize(&value.entries[0].fields).unwrap());
+        assert_eq!(sha_256(&content).to_vec(), value.entries[0].hash.0);
+
+        // the log is only readable with the owner's key
+        let log_msg = QueryMsg::AccessLog {
+            address: HumanAddr("borrower".to_string()),
+            key: "wrong".to_string(),
+            page: None,
+            page_size: 10,
+        };
+        assert!(query(&deps, log_msg).is_err());
     }
 
     #[test]
-    fn handle_revoke_permit() {
-        // First we init
-        let mut deps = mock_dependencies(45, &coins(2, "token"));
+    fn pay_for_read() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let read_fee = ReadFee {
+            token: Snip20Token {
+                address: HumanAddr("fee_token".to_string()),
+                code_hash: "fee_token_hash".to_string(),
+            },
+            amount: Uint128(100),
+            treasury: HumanAddr("treasury".to_string()),
+            borrower_share: 30,
+        };
         let init_msg = InitMsg {
-            max_size: 10000,
-            prng_seed: "this is a padding".to_string(),
+            read_fee: Some(read_fee.clone()),
+            ..default_init_msg()
         };
-        let env = mock_env(
-            "secret1nl7dnjcs9w2a4mn4q43nwyptf3uyllp3xh44j0",
-            &coins(20, "token"),
-        );
-        let res = init(&mut deps, env, init_msg).unwrap();
-        assert_eq!(0, res.messages.len());
+        let env = mock_env("creator", &coins(20, "token"));
+        let res = init(&mut deps, env.clone(), init_msg).unwrap();
+        let register = register_receive_msg(
+            env.contract_code_hash,
+            None,
+            BLOCK_SIZE,
+            "fee_token_hash".to_string(),
+            HumanAddr("fee_token".to_string()),
+        )
+        .unwrap();
+        assert_eq!(vec![register], res.messages);
 
-        // WE RECORD THE SCORE
-        let _env = mock_env(
-            "secret1nl7dnjcs9w2a4mn4q43nwyptf3uyllp3xh44j0",
-            &coins(20, "token"),
-        );
+        let env = mock_env("oracle", &coins(20, "token"));
         let msg = HandleMsg::Record {
-            score: 300,
-            description: String::from("Good job dude"),
+            subject: HumanAddr("borrower".to_string()),
+            score: 700,
+            band: ScoreBand::VeryGood,
+            description: "This describes your score".to_string(),
+            components: None,
+            loan_ceiling: None,
+            attestation: None,
         };
-        let record_res = handle(&mut deps, _env, msg).unwrap();
-        assert_eq!(0, record_res.messages.len());
+        handle(&mut deps, env, msg).unwrap();
 
-        // Revoke a permission
-        let __env = mock_env("creator", &coins(20, "token"));
-        let revoke_msg = HandleMsg::RevokePermit {
-            permit_name: String::from("test"),
+        let receive_msg = |amount| HandleMsg::Receive {
+            sender: HumanAddr("lender".to_string()),
+            from: HumanAddr("lender".to_string()),
+            amount: Uint128(amount),
+            msg: Some(
+                to_binary(&ReceiveMsg::PayForRead {
+                    borrower: HumanAddr("borrower".to_string()),
+                })
+                .unwrap(),
+            ),
+        };
+
+        // only the fee token can pay, and only the full fee
+        let env = mock_env("other_token", &coins(20, "token"));
+        let res = handle(&mut deps, env, receive_msg(100));
+        assert_eq!(res.unwrap_err(), ContractError::FeeNotAccepted.into());
+        let env = mock_env("fee_token", &coins(20, "token"));
+        let res = handle(&mut deps, env.clone(), receive_msg(99));
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::InsufficientFee {
+                amount: 99,
+                fee: 100
+            }
+            .into()
+        );
+
+        // the borrower has to opt in first
+        let res = handle(&mut deps, env.clone(), receive_msg(100));
+        assert_eq!(res.unwrap_err(), ContractError::PaidReadsNotAccepted.into());
+        let opt_in_msg = |accept| HandleMsg::SetPaidReads {
+            accept,
             padding: None,
         };
+        handle(&mut deps, mock_env("borrower", &[]), opt_in_msg(true)).unwrap();
 
-        let record_revoke = handle(&mut deps, __env, revoke_msg).unwrap();
+        let res = handle(&mut deps, env.clone(), receive_msg(100)).unwrap();
+        let payout = |recipient: &str, amount| {
+            transfer_msg(
+                HumanAddr(recipient.to_string()),
+                Uint128(amount),
+                None,
+                None,
+                BLOCK_SIZE,
+                "fee_token_hash".to_string(),
+                HumanAddr("fee_token".to_string()),
+            )
+            .unwrap()
+        };
+        assert_eq!(
+            vec![payout("borrower", 30), payout("treasury", 70)],
+            res.messages
+        );
 
-        assert_eq!(0, record_revoke.messages.len());
+        // the payment bought exactly one read
+        let read_msg = HandleMsg::ReadAsGrantee {
+            owner: HumanAddr("borrower".to_string()),
+            padding: None,
+        };
+        let lender_env = mock_env("lender", &coins(20, "token"));
+        handle(&mut deps, lender_env.clone(), read_msg.clone()).unwrap();
+        let res = handle(&mut deps, lender_env, read_msg);
+        assert_eq!(res.unwrap_err(), ContractError::GrantExhausted.into());
 
-        // Check if permit is revoked
-        let storage_key = PREFIX_REVOKED_PERMITS.to_string() + "creator" + "test";
+        // splitting the largest payment doesn't overflow
+        let res = handle(&mut deps, env.clone(), receive_msg(u128::MAX)).unwrap();
+        let borrower_amount = u128::MAX / 100 * 30 + 16;
+        assert_eq!(
+            vec![
+                payout("borrower", borrower_amount),
+                payout("treasury", u128::MAX - borrower_amount)
+            ],
+            res.messages
+        );
 
-        let revoked_permits = deps.storage.get(storage_key.as_bytes()).is_some();
+        // a lender with unlimited reads has nothing to pay for
+        let grant_msg = HandleMsg::GrantAccess {
+            grantee: HumanAddr("lender".to_string()),
+            expires_at: None,
+            max_reads: None,
+            padding: None,
+        };
+        handle(&mut deps, mock_env("borrower", &[]), grant_msg).unwrap();
+        let res = handle(&mut deps, env.clone(), receive_msg(100));
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::GrantAlreadyUnlimited.into()
+        );
 
-        assert_eq!(true, revoked_permits);
+        handle(&mut deps, mock_env("borrower", &[]), opt_in_msg(false)).unwrap();
+        let res = handle(&mut deps, env, receive_msg(100));
+        assert_eq!(res.unwrap_err(), ContractError::PaidReadsNotAccepted.into());
     }
 
     #[test]
-    fn handle_viewing_key() {
-        // First we init
+    fn record_fee() {
         let mut deps = mock_dependencies(20, &coins(2, "token"));
         let init_msg = InitMsg {
-            max_size: 10000,
-            prng_seed: "this is a padding".to_string(),
+            record_fee: Some(Coin::new(1000, "uscrt")),
+            ..default_init_msg()
         };
         let env = mock_env("creator", &coins(20, "token"));
-        let res = init(&mut deps, env, init_msg).unwrap();
-        assert_eq!(0, res.messages.len());
+        init(&mut deps, env, init_msg).unwrap();
 
-        // WE RECORD THE SCORE
-        let _env = mock_env("creator", &coins(20, "token"));
         let msg = HandleMsg::Record {
-            score: 300,
-            description: String::from("Your SCRTSibyl score is FAIR, with a total of 581 points, which qualifies you for a loan of up to $5000 USD. SCRTSibyl computed your score accounting for your Plaid diamond 12.5% apr interest credit card credit card your total current balance of $44520 and your 9 different bank accounts. An error occurred during computation of the metrics: velocity, and your score was rounded down. Try again later or log in using a different account."),
+            subject: HumanAddr("borrower".to_string()),
+            score: 700,
+            band: ScoreBand::VeryGood,
+            description: "This describes your score".to_string(),
+            components: None,
+            loan_ceiling: None,
+            attestation: None,
         };
-        let record_res = handle(&mut deps, _env, msg).unwrap();
-        assert_eq!(0, record_res.messages.len());
+        for funds in &[coins(999, "uscrt"), coins(1000, "token")] {
+            let env = mock_env("oracle", funds);
+            let res = handle(&mut deps, env, msg.clone());
+            assert_eq!(
+                res.unwrap_err(),
+                ContractError::RecordFeeUnpaid {
+                    amount: 1000,
+                    denom: "uscrt".to_string()
+                }
+                .into()
+            );
+        }
+        let env = mock_env("oracle", &coins(1000, "uscrt"));
+        handle(&mut deps, env, msg).unwrap();
 
-        // Create a viewing key
-        let __env = mock_env("creator", &coins(20, "token"));
-        let v_key_msg = HandleMsg::GenerateViewingKey {
-            entropy: "This is a string".to_string(),
-            padding: Some(String::from("Good job dude")),
+        let withdraw_msg = HandleMsg::Withdraw {
+            amount: coins(1000, "uscrt"),
+            recipient: HumanAddr("treasury".to_string()),
+            padding: None,
         };
+        let env = mock_env("oracle", &[]);
+        let res = handle(&mut deps, env, withdraw_msg.clone());
+        assert_eq!(res.unwrap_err(), StdError::unauthorized());
+        let env = mock_env("creator", &[]);
+        let res = handle(&mut deps, env.clone(), withdraw_msg).unwrap();
+        assert_eq!(
+            vec![CosmosMsg::Bank(BankMsg::Send {
+                from_address: env.contract.address,
+                to_address: HumanAddr("treasury".to_string()),
+                amount: coins(1000, "uscrt"),
+            })],
+            res.messages
+        );
+    }
 
-        handle(&mut deps, __env, v_key_msg).unwrap(); //generates key: "api_key_j0y+6OGIPoHIcEEJw3WiM2695AzuNcBu/qjDwDPdwUQ="
+    #[test]
+    fn verify_eligibility() {
+        use crate::interface::ScoreQueryMsg;
+        use secret_toolkit::permit::{PermitParams, PermitSignature, PubKey};
 
-        // Query w Viewing key
-        let query_msg = QueryMsg::Read {
-            address: HumanAddr("creator".to_string()),
-            key: ("api_key_j0y+6OGIPoHIcEEJw3WiM2695AzuNcBu/qjDwDPdwUQ=".to_string()),
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let init_msg = default_init_msg();
+        let env = mock_env("creator", &coins(20, "token"));
+        init(&mut deps, env, init_msg).unwrap();
+
+        let env = mock_env("oracle", &coins(20, "token"));
+        let msg = HandleMsg::Record {
+            subject: HumanAddr("borrower".to_string()),
+            score: 700,
+            band: ScoreBand::VeryGood,
+            description: "This describes your score".to_string(),
+            components: None,
+            loan_ceiling: None,
+            attestation: None,
         };
+        handle(&mut deps, env.clone(), msg).unwrap();
 
-        let res = query(&deps, query_msg).unwrap();
-        let value: ScoreResponse = from_binary(&res).unwrap();
-        assert_eq!(300, value.score.unwrap());
-        assert_eq!("Your SCRTSibyl score is FAIR, with a total of 581 points, which qualifies you for a loan of up to $5000 USD. SCRTSibyl computed your score accounting for your Plaid diamond 12.5% apr interest credit card credit card your total current balance of $44520 and your 9 different bank accounts. An error occurred during computation of the metrics: velocity, and your score was rounded down. Try again later or log in using a different account.", value.description);
+        let borrower = HumanAddr("borrower".to_string());
+        let value = check_eligibility(&deps, &borrower, ScoreBand::Good, None).unwrap();
+        assert_eq!(
+            EligibilityResponse {
+                eligible: true,
+                band: Some(ScoreBand::VeryGood)
+            },
+            value
+        );
+        let value = check_eligibility(&deps, &borrower, ScoreBand::Excellent, None).unwrap();
+        assert!(!value.eligible);
+
+        // a score that is too old for the lender doesn't qualify
+        let mut env = mock_env("anyone", &coins(20, "token"));
+        env.block.time += 10 * SECONDS_PER_DAY;
+        save_block(&mut deps.storage, &env.block).unwrap();
+        let value =
+            check_eligibility(&deps, &borrower, ScoreBand::Good, Some(SECONDS_PER_DAY)).unwrap();
+        assert!(!value.eligible);
+        let nobody = HumanAddr("nobody".to_string());
+        let value = check_eligibility(&deps, &nobody, ScoreBand::Poor, None).unwrap();
+        assert_eq!(
+            EligibilityResponse {
+                eligible: false,
+                band: None
+            },
+            value
+        );
+
+        // the interface serializes to the contract's own query
+        let permit = |permit_name: &str| Permit {
+            params: PermitParams {
+                allowed_tokens: vec![],
+                permit_name: permit_name.to_string(),
+                chain_id: "secret-4".to_string(),
+                permissions: vec![Permission::Balance],
+            },
+            signature: PermitSignature {
+                pub_key: PubKey {
+                    r#type: "tendermint/PubKeySecp256k1".to_string(),
+                    value: Binary::default(),
+                },
+                signature: Binary::default(),
+            },
+        };
+        let interface_msg = ScoreQueryMsg::VerifyEligibility {
+            borrower: borrower.clone(),
+            min_band: ScoreBand::Good,
+            max_age: None,
+            auth: permit("eligibility-loan"),
+        };
+        let query_msg = |permit_name| QueryMsg::VerifyEligibility {
+            borrower: borrower.clone(),
+            min_band: ScoreBand::Good,
+            max_age: None,
+            auth: permit(permit_name),
+        };
+        assert_eq!(
+            to_binary(&interface_msg),
+            to_binary(&query_msg("eligibility-loan"))
+        );
+        assert!(query(&deps, query_msg("eligibility-loan")).is_err());
+
+        // eligibility permits and the others are not interchangeable
+        let wrong_name = |reason: &str| -> StdError {
+            ContractError::PermitInvalid {
+                reason: reason.to_string(),
+            }
+            .into()
+        };
+        assert_eq!(
+            query(&deps, query_msg("balance")).unwrap_err(),
+            wrong_name("VerifyEligibility needs a permit named eligibility...")
+        );
+        let balance_msg = QueryMsg::WithPermit {
+            permit: permit("eligibility-loan"),
+            query: QueryWithPermit::Balance {},
+        };
+        assert_eq!(
+            query(&deps, balance_msg).unwrap_err(),
+            wrong_name("permits named eligibility... only verify eligibility")
+        );
+    }
+
+    #[test]
+    fn score_callbacks() {
+        let mut deps = mock_dependencies(20, &coins(2, "token"));
+        let init_msg = default_init_msg();
+        let env = mock_env("creator", &coins(20, "token"));
+        init(&mut deps, env, init_msg).unwrap();
+
+        let add_msg = |contract: &str| HandleMsg::AddCallbackContract {
+            contract: HumanAddr(contract.to_string()),
+            code_hash: format!("{}_hash", contract),
+            padding: None,
+        };
+        let env = mock_env("borrower", &coins(20, "token"));
+        let res = handle(&mut deps, env, add_msg("loan"));
+        assert_eq!(res.unwrap_err(), StdError::unauthorized());
+        let env = mock_env("creator", &coins(20, "token"));
+        for contract in &["loan", "escrow"] {
+            handle(&mut deps, env.clone(), add_msg(contract)).unwrap();
+        }
+
+        let subscribe_msg = |contract: &str| HandleMsg::Subscribe {
+            contract: HumanAddr(contract.to_string()),
+            padding: None,
+        };
+        let env = mock_env("borrower", &coins(20, "token"));
+        for contract in &["loan", "escrow"] {
+            handle(&mut deps, env.clone(), subscribe_msg(contract)).unwrap();
+        }
+        // contracts the admin hasn't added, or that aren't addresses at all, are refused
+        let res = handle(&mut deps, env.clone(), subscribe_msg("rogue"));
+        assert_eq!(
+            res.unwrap_err(),
+            ContractError::CallbackContractNotFound.into()
+        );
+        assert!(handle(&mut deps, env.clone(), subscribe_msg("x")).is_err());
+        let unsubscribe_msg = HandleMsg::Unsubscribe {
+            contract: HumanAddr("escrow".to_string()),
+            padding: None,
+        };
+        handle(&mut deps, env, unsubscribe_msg).unwrap();
+
+        let env = mock_env("oracle", &coins(20, "token"));
+        let msg = |subject: &str| HandleMsg::Record {
+            subject: HumanAddr(subject.to_string()),
+            score: 700,
+            band: ScoreBand::VeryGood,
+            description: "This describes your score".to_string(),
+            components: None,
+            loan_ceiling: None,
+            attestation: None,
+        };
+        let res = handle(&mut deps, env.clone(), msg("borrower")).unwrap();
+        assert!(res.messages.is_empty());
+
+        let deliver_msg = |borrower: &str, contract: &str| HandleMsg::DeliverCallback {
+            borrower: HumanAddr(borrower.to_string()),
+            contract: HumanAddr(contract.to_string()),
+            padding: None,
+        };
+        let callback = |band: ScoreBand| {
+            ScoreCallbackMsg::ScoreUpdated {
+                borrower: HumanAddr("borrower".to_string()),
+                band,
+                timestamp: env.block.time,
+            }
+            .to_cosmos_msg("loan_hash".to_string(), HumanAddr("loan".to_string()), None)
+            .unwrap()
+        };
+        let anyone = mock_env("anyone", &[]);
+        let res = handle(&mut deps, anyone.clone(), deliver_msg("borrower", "loan")).unwrap();
+        assert_eq!(vec![callback(ScoreBand::VeryGood)], res.messages);
+
+        // each update is delivered once, and only to the contracts still subscribed
+        for contract in &["loan", "escrow"] {
+            let res = handle(&mut deps, anyone.clone(), deliver_msg("borrower", contract));
+            assert_eq!(res.unwrap_err(), ContractError::CallbackNotPending.into());
+        }
+
+        // other users' scores don't notify the subscribers
+        handle(&mut deps, env.clone(), msg("other_borrower")).unwrap();
+        let res = handle(
+            &mut deps,
+            anyone.clone(),
+            deliver_msg("other_borrower", "loan"),
+        );
+        assert_eq!(res.unwrap_err(), ContractError::CallbackNotPending.into());
+
+        // a subscriber that rejects its callbacks only fails the deliveries, which run in
+        // transactions of their own, so the borrower can't use it to block a downgrade
+        let downgrade = HandleMsg::Record {
+            subject: HumanAddr("borrower".to_string()),
+            score: 450,
+            band: ScoreBand::Poor,
+            description: "This describes your score".to_string(),
+            components: None,
+            loan_ceiling: None,
+            attestation: None,
+        };
+        handle(&mut deps, env.clone(), msg("borrower")).unwrap();
+        let res = handle(&mut deps, env.clone(), downgrade).unwrap();
+        assert!(res.messages.is_empty());
+        let value = query_read(&deps, &HumanAddr("borrower".to_string()), env.block.time).unwrap();
+        assert_eq!(Some(ScoreBand::Poor), value.band);
+        // updates that pile up meanwhile are delivered once, with the latest score
+        let res = handle(&mut deps, anyone.clone(), deliver_msg("borrower", "loan")).unwrap();
+        assert_eq!(vec![callback(ScoreBand::Poor)], res.messages);
+
+        // nothing is sent to a contract the admin has taken out since the score was recorded
+        handle(&mut deps, env.clone(), msg("borrower")).unwrap();
+        let remove_msg = HandleMsg::RemoveCallbackContract {
+            contract: HumanAddr("loan".to_string()),
+            padding: None,
+        };
+        handle(&mut deps, mock_env("creator", &[]), remove_msg).unwrap();
+        let res = handle(&mut deps, anyone, deliver_msg("borrower", "loan")).unwrap();
+        assert!(res.messages.is_empty());
+
+        let env = mock_env("creator", &coins(20, "token"));
+        for i in 0..MAX_SUBSCRIBERS {
+            handle(&mut deps, env.clone(), add_msg(&format!("contract_{}", i))).unwrap();
+        }
+        let env = mock_env("borrower", &coins(20, "token"));
+        for i in 0..MAX_SUBSCRIBERS {
+            let res = handle(
+                &mut deps,
+                env.clone(),
+                subscribe_msg(&format!("contract_{}", i)),
+            );
+            if i + 1 < MAX_SUBSCRIBERS {
+                res.unwrap();
+            } else {
+                assert_eq!(
+                    res.unwrap_err(),
+                    ContractError::TooManySubscribers {
+                        max: MAX_SUBSCRIBERS
+                    }
+                    .into()
+                );
+            }
+        }
     }
 }
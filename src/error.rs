@@ -0,0 +1,163 @@
+use cosmwasm_std::StdError;
+use snafu::Snafu;
+
+pub type ContractResult<T> = Result<T, ContractError>;
+
+/// Errors raised by the contract logic itself.
+///
+/// `handle`, `query` and `init` convert them into a `StdError`, the only error type the
+/// `cosmwasm-std` entry points can return. Apart from `Unauthorized` and wrapped `Std`
+/// errors, which map onto their `StdError` counterparts, each error becomes a
+/// `GenericErr` whose message starts with the stable code returned by
+/// [`ContractError::code`], e.g. `description_too_long: Description is ...`, so clients can
+/// match on the code rather than on the wording.
+#[derive(Snafu, Debug, PartialEq)]
+#[snafu(visibility = "pub(crate)")]
+pub enum ContractError {
+    #[snafu(display("Unauthorized"))]
+    Unauthorized,
+    #[snafu(display("The contract is stopped, this action is not allowed at the moment"))]
+    ContractStopped,
+    #[snafu(display("Invalid max_size. Must be in the range of 1..65535."))]
+    InvalidMaxSize,
+    #[snafu(display(
+        "Description is {} bytes long, the maximum allowed is {} bytes",
+        len,
+        max_size
+    ))]
+    DescriptionTooLong { len: usize, max_size: u16 },
+    #[snafu(display(
+        "Invalid score range. min_score ({}) may not exceed max_score ({})",
+        min_score,
+        max_score
+    ))]
+    InvalidScoreRange { min_score: u64, max_score: u64 },
+    #[snafu(display(
+        "Score {} is outside the accepted range of {}..={}",
+        score,
+        min_score,
+        max_score
+    ))]
+    ScoreOutOfRange {
+        score: u64,
+        min_score: u64,
+        max_score: u64,
+    },
+    #[snafu(display(
+        "Score component weights add up to {}%, they may not exceed 100%",
+        total_weight
+    ))]
+    InvalidComponents { total_weight: u64 },
+    #[snafu(display("Invalid score attestation: {}", reason))]
+    AttestationInvalid { reason: String },
+    #[snafu(display("This score attestation has already been used"))]
+    AttestationReplayed,
+    #[snafu(display("This score attestation is older than the validity window"))]
+    AttestationExpired,
+    #[snafu(display("This score attestation is not newer than the score on record"))]
+    AttestationOutdated,
+    #[snafu(display("An address may hold at most {} named viewing keys", max))]
+    TooManyViewingKeys { max: usize },
+    #[snafu(display(
+        "The purpose is {} bytes long, the maximum allowed is {} bytes",
+        len,
+        max_size
+    ))]
+    PurposeTooLong { len: usize, max_size: usize },
+    #[snafu(display("An address may have at most {} subscribed contracts", max))]
+    TooManySubscribers { max: usize },
+    #[snafu(display("This contract has not been added as a callback contract by the admin"))]
+    CallbackContractNotFound,
+    #[snafu(display("No score update for this borrower is waiting to be sent to this contract"))]
+    CallbackNotPending,
+    #[snafu(display("No pending score request from this lender"))]
+    RequestNotFound,
+    #[snafu(display("The borrower's share of a fee must be a percentage, got {}", share))]
+    InvalidFeeShare { share: u8 },
+    #[snafu(display("This contract does not accept payments in this token"))]
+    FeeNotAccepted,
+    #[snafu(display("Paid {}, but a read costs {}", amount, fee))]
+    InsufficientFee { amount: u128, fee: u128 },
+    #[snafu(display("Tokens sent to this contract need a receive msg saying what they pay for"))]
+    ReceiveMsgMissing,
+    #[snafu(display("This borrower does not accept paid reads of their score"))]
+    PaidReadsNotAccepted,
+    #[snafu(display("Your grant already allows unlimited reads of this score"))]
+    GrantAlreadyUnlimited,
+    #[snafu(display("Recording a score costs {}{}", amount, denom))]
+    RecordFeeUnpaid { amount: u128, denom: String },
+    #[snafu(display("No score recorded for this address"))]
+    NotFound,
+    #[snafu(display("Invalid permit: {}", reason))]
+    PermitInvalid { reason: String },
+    #[snafu(display("No permission to {}, got permissions {}", action, permissions))]
+    PermissionDenied { action: String, permissions: String },
+    #[snafu(display("The score is older than the validity window and can no longer be shared"))]
+    ScoreExpired,
+    #[snafu(display("Read access to this score has expired"))]
+    GrantExpired,
+    #[snafu(display("All reads allowed by this grant have been used"))]
+    GrantExhausted,
+    #[snafu(display(
+        "This grant has a read limit and can only be used with the read_as_grantee handle message"
+    ))]
+    GrantReadLimited,
+    #[snafu(display("{}", source))]
+    Std { source: StdError },
+}
+
+impl ContractError {
+    /// A stable, machine readable identifier for the error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ContractError::Unauthorized => "unauthorized",
+            ContractError::ContractStopped => "contract_stopped",
+            ContractError::InvalidMaxSize => "invalid_max_size",
+            ContractError::DescriptionTooLong { .. } => "description_too_long",
+            ContractError::InvalidScoreRange { .. } => "invalid_score_range",
+            ContractError::ScoreOutOfRange { .. } => "score_out_of_range",
+            ContractError::InvalidComponents { .. } => "invalid_components",
+            ContractError::AttestationInvalid { .. } => "attestation_invalid",
+            ContractError::AttestationReplayed => "attestation_replayed",
+            ContractError::AttestationExpired => "attestation_expired",
+            ContractError::AttestationOutdated => "attestation_outdated",
+            ContractError::TooManyViewingKeys { .. } => "too_many_viewing_keys",
+            ContractError::PurposeTooLong { .. } => "purpose_too_long",
+            ContractError::TooManySubscribers { .. } => "too_many_subscribers",
+            ContractError::CallbackContractNotFound => "callback_contract_not_found",
+            ContractError::CallbackNotPending => "callback_not_pending",
+            ContractError::RequestNotFound => "request_not_found",
+            ContractError::InvalidFeeShare { .. } => "invalid_fee_share",
+            ContractError::FeeNotAccepted => "fee_not_accepted",
+            ContractError::InsufficientFee { .. } => "insufficient_fee",
+            ContractError::ReceiveMsgMissing => "receive_msg_missing",
+            ContractError::PaidReadsNotAccepted => "paid_reads_not_accepted",
+            ContractError::GrantAlreadyUnlimited => "grant_already_unlimited",
+            ContractError::RecordFeeUnpaid { .. } => "record_fee_unpaid",
+            ContractError::NotFound => "not_found",
+            ContractError::PermitInvalid { .. } => "permit_invalid",
+            ContractError::PermissionDenied { .. } => "permission_denied",
+            ContractError::ScoreExpired => "score_expired",
+            ContractError::GrantExpired => "grant_expired",
+            ContractError::GrantExhausted => "grant_exhausted",
+            ContractError::GrantReadLimited => "grant_read_limited",
+            ContractError::Std { .. } => "std_error",
+        }
+    }
+}
+
+impl From<StdError> for ContractError {
+    fn from(source: StdError) -> Self {
+        ContractError::Std { source }
+    }
+}
+
+impl From<ContractError> for StdError {
+    fn from(err: ContractError) -> Self {
+        match err {
+            ContractError::Std { source } => source,
+            ContractError::Unauthorized => StdError::unauthorized(),
+            err => StdError::generic_err(format!("{}: {}", err.code(), err)),
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! Types for contracts that query this one or subscribe to score updates, e.g. a loan contract
+//! checking a borrower before releasing funds:
+//!
+//! ```ignore
+//! let response: EligibilityResponse = ScoreQueryMsg::VerifyEligibility {
+//!     borrower,
+//!     min_band: ScoreBand::Good,
+//!     max_age: None,
+//!     auth: permit,
+//! }
+//! .query(&deps.querier, score_contract_hash, score_contract_address)?;
+//! ```
+//!
+//! `auth` is a permit with the `balance` permission whose name starts with
+//! [`ELIGIBILITY_PERMIT_PREFIX`], e.g. `eligibility-loan`. Such permits only answer
+//! `VerifyEligibility`, and it accepts no other permits, so handing one out never reveals more
+//! than the band.
+//!
+//! Queries don't tell the queried contract who asks, so `auth` works for whoever holds it.
+//! The borrower hands the permit to the contract that is meant to use it and can revoke it
+//! by name with `RevokePermit`.
+
+use cosmwasm_std::HumanAddr;
+use schemars::JsonSchema;
+use secret_toolkit::utils::{HandleCallback, Query};
+use serde::{Deserialize, Serialize};
+
+pub use crate::contract::ELIGIBILITY_PERMIT_PREFIX;
+pub use crate::msg::{EligibilityResponse, ScoreBand};
+pub use secret_toolkit::permit::Permit;
+
+/// The queries of this contract meant for other contracts. They serialize the same as the
+/// corresponding `QueryMsg` variants.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ScoreQueryMsg {
+    VerifyEligibility {
+        borrower: HumanAddr,
+        min_band: ScoreBand,
+        max_age: Option<u64>,
+        auth: Permit,
+    },
+}
+
+impl Query for ScoreQueryMsg {
+    const BLOCK_SIZE: usize = 256;
+}
+
+/// Sent to every contract a borrower subscribed with `Subscribe` when their score is recorded.
+/// The admin has to add the contract with `AddCallbackContract` before it can be subscribed.
+/// Callbacks go out with `DeliverCallback`, in a transaction of their own, and carry the
+/// latest score at the time of delivery.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ScoreCallbackMsg {
+    ScoreUpdated {
+        borrower: HumanAddr,
+        band: ScoreBand,
+        timestamp: u64,
+    },
+}
+
+impl HandleCallback for ScoreCallbackMsg {
+    const BLOCK_SIZE: usize = 256;
+}
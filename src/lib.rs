@@ -1,4 +1,6 @@
 pub mod contract;
+pub mod error;
+pub mod interface;
 pub mod msg;
 pub mod state;
 mod utils;
@@ -1,20 +1,229 @@
 use crate::viewing_key::ViewingKey;
-use cosmwasm_std::{HumanAddr, StdResult};
+use cosmwasm_std::{from_slice, Binary, Coin, HumanAddr, StdResult, Uint128};
 use schemars::JsonSchema;
 use secret_toolkit::permit::Permit;
 use serde::{Deserialize, Serialize};
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InitMsg {
+    pub admin: Option<HumanAddr>,
     pub max_size: u16,
+    /// How long, in seconds, a recorded score stays valid
+    pub score_validity: Option<u64>,
+    pub min_score: Option<u64>,
+    pub max_score: Option<u64>,
+    /// Smallest group of users aggregate statistics are disclosed for
+    pub k_anonymity: Option<u64>,
+    /// Lets lenders buy reads of a borrower's score with a SNIP-20 token
+    pub read_fee: Option<ReadFee>,
+    /// Native funds that must be sent along with every `Record`
+    pub record_fee: Option<Coin>,
     pub prng_seed: String,
+    pub oracles: Option<Vec<HumanAddr>>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum HandleMsg {
     Record {
+        subject: HumanAddr,
         score: u64,
+        /// The oracle's rating of the score, stored as is
+        band: ScoreBand,
         description: String,
+        components: Option<Vec<ScoreComponent>>,
+        loan_ceiling: Option<u64>,
+        /// Lets anyone, typically the scored user, submit a score signed by an oracle
+        attestation: Option<ScoreAttestation>,
+    },
+
+    AddOracle {
+        address: HumanAddr,
+        padding: Option<String>,
+    },
+
+    AddOracleKey {
+        /// The address attested scores are credited to
+        oracle: HumanAddr,
+        /// Compressed secp256k1 public key
+        pubkey: Binary,
+        padding: Option<String>,
+    },
+
+    RemoveOracleKey {
+        pubkey: Binary,
+        padding: Option<String>,
+    },
+
+    RemoveOracle {
+        address: HumanAddr,
+        padding: Option<String>,
+    },
+
+    /// Lets borrowers subscribe `contract`, whose callbacks are sent with `code_hash`.
+    AddCallbackContract {
+        contract: HumanAddr,
+        code_hash: String,
+        padding: Option<String>,
+    },
+
+    /// Stops the callbacks to `contract`, e.g. when it misbehaves.
+    RemoveCallbackContract {
+        contract: HumanAddr,
+        padding: Option<String>,
+    },
+
+    SetMaxSize {
+        max_size: u16,
+        padding: Option<String>,
+    },
+
+    SetScoreValidity {
+        score_validity: u64,
+        padding: Option<String>,
+    },
+
+    SetKAnonymity {
+        k_anonymity: u64,
+        padding: Option<String>,
+    },
+
+    SetContractStatus {
+        level: ContractStatus,
+        padding: Option<String>,
+    },
+
+    /// `None` stops accepting payments for reads.
+    SetReadFee {
+        read_fee: Option<ReadFee>,
+        padding: Option<String>,
+    },
+
+    /// `None` makes recording free.
+    SetRecordFee {
+        record_fee: Option<Coin>,
+        padding: Option<String>,
+    },
+
+    /// Sends native funds collected from record fees out of the contract.
+    Withdraw {
+        amount: Vec<Coin>,
+        recipient: HumanAddr,
+        padding: Option<String>,
+    },
+
+    SetScoreRange {
+        min_score: u64,
+        max_score: u64,
+        padding: Option<String>,
+    },
+
+    /// Upgrades the records of `addresses` to the current layout. Storage can't be iterated,
+    /// so the admin lists the addresses, e.g. the senders of earlier `Record` transactions.
+    /// Addresses without a record, or with an up to date one, are skipped.
+    ///
+    /// Deployments from before versioning have no admin. The first call on one of them makes
+    /// the sender admin if `legacy_prng_seed` is the `prng_seed` it was instantiated with.
+    MigrateStorage {
+        addresses: Vec<HumanAddr>,
+        legacy_prng_seed: Option<String>,
+        padding: Option<String>,
+    },
+
+    ChangeAdmin {
+        address: HumanAddr,
+        padding: Option<String>,
+    },
+
+    AcceptAdmin {
+        padding: Option<String>,
+    },
+
+    /// A grant without `max_reads` can also be read through the `ReadAsGrantee` query, and
+    /// such reads don't show up in the access log.
+    GrantAccess {
+        grantee: HumanAddr,
+        expires_at: Option<u64>,
+        max_reads: Option<u64>,
+        padding: Option<String>,
+    },
+
+    RevokeAccess {
+        grantee: HumanAddr,
+        padding: Option<String>,
+    },
+
+    /// Asks a borrower for a single read of their score. A new request from the same lender
+    /// replaces the pending one, and once `MAX_PENDING_REQUESTS` are pending the oldest one is
+    /// dropped.
+    RequestScore {
+        borrower: HumanAddr,
+        purpose: String,
+        /// Also when the grant expires if the request is approved. Requests expire after
+        /// `MAX_REQUEST_LIFETIME` seconds at the latest.
+        expires_at: Option<u64>,
+        padding: Option<String>,
+    },
+
+    /// Grants the requesting lender a single read of the sender's score. If the lender already
+    /// holds an unexpired grant, the read is added to it and its other terms are kept.
+    ApproveRequest {
+        lender: HumanAddr,
+        padding: Option<String>,
+    },
+
+    DenyRequest {
+        lender: HumanAddr,
+        padding: Option<String>,
+    },
+
+    /// Has `contract` notified with a `ScoreCallbackMsg` whenever the sender's score is
+    /// recorded. Only contracts the admin added with `AddCallbackContract` can be subscribed.
+    Subscribe {
+        contract: HumanAddr,
+        padding: Option<String>,
+    },
+
+    Unsubscribe {
+        contract: HumanAddr,
+        padding: Option<String>,
+    },
+
+    /// Sends `contract` the `ScoreCallbackMsg` for `borrower`'s latest score. `Record` only
+    /// queues callbacks, so a subscriber that fails can't hold up recording. Anyone may
+    /// deliver them, typically the oracle right after recording.
+    DeliverCallback {
+        borrower: HumanAddr,
+        contract: HumanAddr,
+        padding: Option<String>,
+    },
+
+    ReadAsGrantee {
+        owner: HumanAddr,
+        padding: Option<String>,
+    },
+
+    /// Lets lenders buy reads of the sender's score with `ReceiveMsg::PayForRead`, off by
+    /// default.
+    SetPaidReads {
+        accept: bool,
+        padding: Option<String>,
+    },
+
+    /// Called by the fee token when a lender sends tokens to this contract, with a
+    /// `ReceiveMsg` in `msg`.
+    Receive {
+        sender: HumanAddr,
+        from: HumanAddr,
+        amount: Uint128,
+        msg: Option<Binary>,
+    },
+
+    /// Like `ReadAsGrantee`, limited to the given fields, all of them by default. Both handles
+    /// record the read in the owner's access log.
+    ReadScoreLogged {
+        owner: HumanAddr,
+        fields: Option<Vec<ScoreField>>,
+        padding: Option<String>,
     },
 
     WithPermit {
@@ -27,10 +236,68 @@ pub enum HandleMsg {
         padding: Option<String>,
     },
 
+    /// Without a name, replaces the unnamed key. Named keys are kept side by side.
     GenerateViewingKey {
         entropy: String,
+        name: Option<String>,
+        padding: Option<String>,
+    },
+
+    SetViewingKey {
+        key: String,
+        name: Option<String>,
+        padding: Option<String>,
+    },
+
+    DeleteViewingKey {
+        name: Option<String>,
         padding: Option<String>,
     },
+
+    /// Erases the sender's score, its history and access log, the grants they issued and their
+    /// viewing keys.
+    /// Past transactions stay on chain, but the contract no longer serves any of this data.
+    DeleteMyScore {
+        padding: Option<String>,
+    },
+}
+
+/// What lenders can ask for when sending the fee token to this contract.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveMsg {
+    /// Unlocks one more read of the borrower's score for the sender of the tokens, if the
+    /// borrower accepts paid reads
+    PayForRead { borrower: HumanAddr },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Snip20Token {
+    pub address: HumanAddr,
+    pub code_hash: String,
+}
+
+/// The price of a single read of a borrower's score. Every payment is split between the
+/// borrower and the treasury.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReadFee {
+    pub token: Snip20Token,
+    pub amount: Uint128,
+    pub treasury: HumanAddr,
+    /// Percentage of every payment that goes to the borrower
+    pub borrower_share: u8,
+}
+
+/// Circuit breaker for emergencies such as a compromised oracle.
+/// - `StopRecording` refuses new scores, everything else keeps working
+/// - `StopAll` also refuses queries. Users can still revoke permits and grants, delete
+///   viewing keys and their data, and the admin can still be changed.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, JsonSchema, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ContractStatus {
+    Normal,
+    StopRecording,
+    StopAll,
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
@@ -48,6 +315,122 @@ pub enum HandleAnswer {
         status: String,
     },
 
+    AddOracle {
+        status: ResponseStatus,
+    },
+
+    RemoveOracle {
+        status: ResponseStatus,
+    },
+
+    AddCallbackContract {
+        status: ResponseStatus,
+    },
+
+    RemoveCallbackContract {
+        status: ResponseStatus,
+    },
+
+    AddOracleKey {
+        status: ResponseStatus,
+    },
+
+    RemoveOracleKey {
+        status: ResponseStatus,
+    },
+
+    SetMaxSize {
+        status: ResponseStatus,
+    },
+
+    SetScoreValidity {
+        status: ResponseStatus,
+    },
+
+    SetKAnonymity {
+        status: ResponseStatus,
+    },
+
+    SetContractStatus {
+        status: ResponseStatus,
+    },
+
+    SetReadFee {
+        status: ResponseStatus,
+    },
+
+    SetRecordFee {
+        status: ResponseStatus,
+    },
+
+    Withdraw {
+        status: ResponseStatus,
+    },
+
+    SetPaidReads {
+        status: ResponseStatus,
+    },
+
+    Receive {
+        status: ResponseStatus,
+    },
+
+    SetScoreRange {
+        status: ResponseStatus,
+    },
+
+    MigrateStorage {
+        migrated: u32,
+    },
+
+    ChangeAdmin {
+        status: ResponseStatus,
+    },
+
+    AcceptAdmin {
+        status: ResponseStatus,
+    },
+
+    GrantAccess {
+        status: ResponseStatus,
+    },
+
+    RevokeAccess {
+        status: ResponseStatus,
+    },
+
+    RequestScore {
+        status: ResponseStatus,
+    },
+
+    ApproveRequest {
+        status: ResponseStatus,
+    },
+
+    DenyRequest {
+        status: ResponseStatus,
+    },
+
+    Subscribe {
+        status: ResponseStatus,
+    },
+
+    Unsubscribe {
+        status: ResponseStatus,
+    },
+
+    DeliverCallback {
+        status: ResponseStatus,
+    },
+
+    ReadAsGrantee {
+        data: ScoreResponse,
+    },
+
+    ReadScoreLogged {
+        data: ScoreResponse,
+    },
+
     RevokePermit {
         status: ResponseStatus,
     },
@@ -56,9 +439,35 @@ pub enum HandleAnswer {
         data: StdResult<ScoreResponse>,
     },
 
+    PermitHistory {
+        data: StdResult<HistoryResponse>,
+    },
+
+    PermitGrants {
+        data: StdResult<GrantsResponse>,
+    },
+
+    PermitBand {
+        data: StdResult<BandResponse>,
+    },
+
     GenerateViewingKey {
         key: ViewingKey,
     },
+
+    SetViewingKey {
+        status: ResponseStatus,
+    },
+
+    DeleteViewingKey {
+        status: ResponseStatus,
+    },
+
+    DeleteMyScore {
+        status: ResponseStatus,
+        /// Whether a score was on record
+        deleted: bool,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -75,23 +484,203 @@ pub enum QueryMsg {
         address: HumanAddr,
         key: String,
     },
+
+    ReadHistory {
+        address: HumanAddr,
+        key: String,
+        page: Option<u32>,
+        page_size: u32,
+    },
+
+    /// Only for grants without `max_reads`. Freshness is judged at the latest transaction to
+    /// the contract, see `ScoreResponse::as_of`. Queries can't write to storage, so these
+    /// reads are not recorded in the owner's access log.
+    ReadAsGrantee {
+        owner: HumanAddr,
+        grantee: HumanAddr,
+        key: String,
+    },
+
+    /// Names of the named viewing keys of an address. Any of its keys authenticates.
+    ListViewingKeys {
+        address: HumanAddr,
+        key: String,
+    },
+
+    /// Admin only. Pages through everyone with a score on record, for audits. `start` is the
+    /// `next` position returned with the previous page.
+    ListScores {
+        address: HumanAddr,
+        key: String,
+        start: Option<u32>,
+        limit: Option<u32>,
+    },
+
+    /// Who read the score of an address through the `ReadAsGrantee` or `ReadScoreLogged`
+    /// handles, most recent first. Reads through the `ReadAsGrantee` query are not logged.
+    AccessLog {
+        address: HumanAddr,
+        key: String,
+        page: Option<u32>,
+        page_size: u32,
+    },
+
+    /// Score requests lenders have sent to an address that are still waiting for an answer.
+    PendingRequests {
+        address: HumanAddr,
+        key: String,
+    },
+
+    /// For other contracts: whether the borrower has a score of at least `min_band` that is
+    /// at most `max_age` seconds old, the validity window by default. `auth` is a `balance`
+    /// permit the borrower signed with a name starting with `eligibility`, which no other
+    /// query accepts. See `crate::interface`.
+    VerifyEligibility {
+        borrower: HumanAddr,
+        min_band: ScoreBand,
+        max_age: Option<u64>,
+        auth: Permit,
+    },
 }
 
+/// Permits reuse the SNIP-20 permission names, which this contract reads as follows:
+/// - `balance`: `Band`, i.e. the band of the latest score and its freshness. This is the
+///   permission to sign for a lender.
+/// - `history`: `Balance` and `History`, the raw scores and percentile without the breakdown
+/// - `allowance`: `Grants`, the read grants the signer has issued
+/// - `owner`: `Breakdown`, the latest score with its description, components and loan
+///   ceiling. Like SNIP-20 `owner` permits these are for the borrower's own apps and are not
+///   meant to be handed out.
+///
+/// The admin lists everyone's score metadata with a viewing key through
+/// `QueryMsg::ListScores`, no permit grants it. Permits named `eligibility...` are rejected
+/// here, they only answer `QueryMsg::VerifyEligibility`.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryWithPermit {
+    Band {},
     Balance {},
+    Breakdown {},
+    History { page: Option<u32>, page_size: u32 },
+    Grants {},
 }
 
 impl QueryMsg {
     pub fn get_validation_params(&self) -> (Vec<&HumanAddr>, ViewingKey) {
         match self {
             Self::Read { address, key, .. } => (vec![address], ViewingKey(key.clone())),
+            Self::ReadHistory { address, key, .. } => (vec![address], ViewingKey(key.clone())),
+            Self::ReadAsGrantee { grantee, key, .. } => (vec![grantee], ViewingKey(key.clone())),
+            Self::ListViewingKeys { address, key } => (vec![address], ViewingKey(key.clone())),
+            Self::ListScores { address, key, .. } => (vec![address], ViewingKey(key.clone())),
+            Self::PendingRequests { address, key } => (vec![address], ViewingKey(key.clone())),
+            Self::AccessLog { address, key, .. } => (vec![address], ViewingKey(key.clone())),
             _ => panic!("This query type does not require authentication"),
         }
     }
 }
 
+/// Qualitative rating of a score, as given by the oracle that recorded it.
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, JsonSchema,
+)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ScoreBand {
+    Poor,
+    Fair,
+    Good,
+    VeryGood,
+    Excellent,
+}
+
+pub const SCORE_BANDS: [ScoreBand; 5] = [
+    ScoreBand::Poor,
+    ScoreBand::Fair,
+    ScoreBand::Good,
+    ScoreBand::VeryGood,
+    ScoreBand::Excellent,
+];
+
+impl ScoreBand {
+    /// The band an oracle named in a description like "Your SCRTSibyl score is FAIR, ...".
+    /// Records from before `Record` carried the band only have it there, if at all.
+    pub fn named_in(description: &str) -> Option<Self> {
+        let name = description.split("score is ").nth(1)?.split(',').next()?;
+        let name = format!("\"{}\"", name.trim().replace(' ', "_"));
+        from_slice(name.as_bytes()).ok()
+    }
+
+    /// Rough band of a score, for legacy records whose description names none. Oracles rate
+    /// scores themselves and can disagree with these thresholds.
+    pub fn from_score(score: u64) -> Self {
+        match score {
+            0..=499 => ScoreBand::Poor,
+            500..=559 => ScoreBand::Fair,
+            560..=649 => ScoreBand::Good,
+            650..=739 => ScoreBand::VeryGood,
+            _ => ScoreBand::Excellent,
+        }
+    }
+}
+
+/// The parts of a score a grantee can read.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ScoreField {
+    Score,
+    Band,
+    Description,
+    Components,
+    LoanCeiling,
+}
+
+pub const SCORE_FIELDS: [ScoreField; 5] = [
+    ScoreField::Score,
+    ScoreField::Band,
+    ScoreField::Description,
+    ScoreField::Components,
+    ScoreField::LoanCeiling,
+];
+
+/// One of the metrics the oracle combined into the overall score, e.g. credit mix or
+/// velocity. `weight` is the share, in percent, the metric contributed to the score.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ScoreComponent {
+    pub name: String,
+    pub score: u64,
+    pub weight: u8,
+}
+
+/// An oracle's signature over an `AttestationPayload`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ScoreAttestation {
+    /// Compressed secp256k1 public key of the signing oracle
+    pub pubkey: Binary,
+    /// When the oracle computed the score
+    pub timestamp: u64,
+    pub nonce: u64,
+    /// 64 byte compact secp256k1 signature of the sha256 hash of the JSON encoded payload
+    pub signature: Binary,
+}
+
+/// The content an oracle signs. It covers the whole record, so a user cannot pair a genuine
+/// score with a description or breakdown of their own, and the contract it is meant for, so
+/// it cannot be replayed on another deployment. `components` is the empty list when the
+/// record has none.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AttestationPayload {
+    pub chain_id: String,
+    pub contract: HumanAddr,
+    pub subject: HumanAddr,
+    pub score: u64,
+    pub band: ScoreBand,
+    pub description: String,
+    pub components: Vec<ScoreComponent>,
+    pub loan_ceiling: Option<u64>,
+    pub timestamp: u64,
+    pub nonce: u64,
+}
+
 // We define a custom struct for each query response
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct ScoreResponse {
@@ -99,17 +688,143 @@ pub struct ScoreResponse {
     pub timestamp: Option<u64>,
     pub status: String,
     pub description: String,
+    pub expires_at: Option<u64>,
+    pub is_stale: bool,
+    /// Time `is_stale` was judged at. Queries can't see the current block and use the block
+    /// of the latest transaction to the contract instead, so for query responses this can lag
+    /// behind. Grantees who must not rely on an expired score should compare `expires_at`
+    /// with their own clock, or read through the `ReadAsGrantee` handle message.
+    pub as_of: u64,
+    pub band: Option<ScoreBand>,
+    pub components: Option<Vec<ScoreComponent>>,
+    /// Largest loan, in USD, the oracle recommends for this score
+    pub loan_ceiling: Option<u64>,
+    /// Approximate share of users, in percent, with a lower score. Withheld while there are
+    /// fewer scores on record than the k-anonymity threshold.
+    pub percentile: Option<u8>,
+}
+
+/// `ScoreResponse` reduced to what a `balance` permit discloses.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BandResponse {
+    pub band: Option<ScoreBand>,
+    pub timestamp: Option<u64>,
+    pub expires_at: Option<u64>,
+    pub is_stale: bool,
+    pub as_of: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ScoreHistoryEntry {
+    pub score: u64,
+    pub timestamp: u64,
+    pub description: String,
+    pub oracle: HumanAddr,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HistoryResponse {
+    pub entries: Vec<ScoreHistoryEntry>,
+    pub total: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct GrantInfo {
+    pub grantee: HumanAddr,
+    pub expires_at: Option<u64>,
+    pub max_reads: Option<u64>,
+    pub reads: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct GrantsResponse {
+    pub grants: Vec<GrantInfo>,
+}
+
+/// What an audit gets to see of a score. The description is left out.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ScoreMetadata {
+    pub address: HumanAddr,
+    pub timestamp: u64,
+    /// The oracle that recorded the latest score
+    pub oracle: Option<HumanAddr>,
+    pub band: ScoreBand,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ListScoresResponse {
+    pub scores: Vec<ScoreMetadata>,
+    pub total: u64,
+    /// Where the next page starts, `None` after the last page. Pages hold fewer than `limit`
+    /// scores where scores were deleted.
+    pub next: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EligibilityResponse {
+    pub eligible: bool,
+    /// Band of the borrower's score, if one is on record
+    pub band: Option<ScoreBand>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AccessLogEntryInfo {
+    pub reader: HumanAddr,
+    pub timestamp: u64,
+    pub fields: Vec<ScoreField>,
+    /// sha256 over the previous entry's hash and this entry, so the log can be checked for
+    /// gaps and alterations
+    pub hash: Binary,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AccessLogResponse {
+    pub entries: Vec<AccessLogEntryInfo>,
+    pub total: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ScoreRequestInfo {
+    pub lender: HumanAddr,
+    pub purpose: String,
+    pub expires_at: Option<u64>,
+    pub requested_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingRequestsResponse {
+    pub requests: Vec<ScoreRequestInfo>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ViewingKeysResponse {
+    /// Whether an unnamed key is set as well
+    pub has_unnamed: bool,
+    pub names: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct StatsResponse {
+    pub contract_status: ContractStatus,
     pub score_count: u64,
     pub max_size: u16,
+    pub score_validity: u64,
+    pub min_score: u64,
+    pub max_score: u64,
+    pub k_anonymity: u64,
+    pub read_fee: Option<ReadFee>,
+    pub record_fee: Option<Coin>,
+    /// Aggregates below are `None` where they would describe fewer than `k_anonymity` users.
+    /// Counts that would let a withheld one be worked out from `score_count` are withheld as
+    /// well, and the mean is rounded to a multiple of ten.
+    pub mean_score: Option<u64>,
+    pub bands: Vec<BandCount>,
+    pub fresh_count: Option<u64>,
+    pub stale_count: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct StateResponse {
-    pub score_count: u64,
-    pub max_size: u16,
-    pub prng_seed: Vec<u8>,
+pub struct BandCount {
+    pub band: ScoreBand,
+    pub count: Option<u64>,
 }
@@ -3,15 +3,44 @@ use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::any::type_name;
 
+use crate::contract::{
+    DEFAULT_K_ANONYMITY, DEFAULT_MAX_SCORE, DEFAULT_MIN_SCORE, DEFAULT_SCORE_VALIDITY,
+};
+use crate::msg::{ContractStatus, ReadFee, ScoreBand, ScoreComponent, ScoreField, SCORE_BANDS};
 use crate::viewing_key::ViewingKey;
-use cosmwasm_std::{CanonicalAddr, HumanAddr, ReadonlyStorage, StdError, StdResult, Storage};
+use cosmwasm_std::{
+    BlockInfo, CanonicalAddr, Coin, HumanAddr, ReadonlyStorage, StdError, StdResult, Storage,
+};
 use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+use secret_toolkit::crypto::sha_256;
 use secret_toolkit::serialization::{Bincode2, Serde};
+use secret_toolkit::storage::{AppendStore, AppendStoreMut};
 
 pub static CONFIG_KEY: &[u8] = b"config";
 pub const PREFIX_CONFIG: &[u8] = b"config";
 pub const KEY_CONSTANTS: &[u8] = b"constants";
+pub const KEY_CONTRACT_VERSION: &[u8] = b"contract_version";
+pub const KEY_DISTRIBUTION: &[u8] = b"distribution";
+pub const KEY_LAST_BLOCK: &[u8] = b"last_block";
+pub const KEY_USER_COUNT: &[u8] = b"user_count";
 pub const PREFIX_VIEWING_KEY: &[u8] = b"viewingkey";
+pub const PREFIX_NAMED_VIEWING_KEYS: &[u8] = b"namedviewingkeys";
+pub const PREFIX_VIEWING_KEY_NAMES: &[u8] = b"viewingkeynames";
+pub const PREFIX_ORACLES: &[u8] = b"oracles";
+pub const PREFIX_ORACLE_KEYS: &[u8] = b"oraclekeys";
+pub const PREFIX_NONCES: &[u8] = b"nonces";
+pub const PREFIX_HISTORY: &[u8] = b"history";
+pub const PREFIX_ACCESS_LOG: &[u8] = b"accesslog";
+pub const PREFIX_GRANTS: &[u8] = b"grants";
+pub const PREFIX_GRANTEES: &[u8] = b"grantees";
+pub const PREFIX_SCORE_REQUESTS: &[u8] = b"scorerequests";
+pub const PREFIX_SUBSCRIBERS: &[u8] = b"subscribers";
+pub const PREFIX_CALLBACK_CONTRACTS: &[u8] = b"callbackcontracts";
+pub const PREFIX_PENDING_CALLBACKS: &[u8] = b"pendingcallbacks";
+pub const PREFIX_PAID_READS: &[u8] = b"paidreads";
+pub const PREFIX_USERS: &[u8] = b"users";
+pub const PREFIX_USER_INDEX: &[u8] = b"scoredusers";
+pub const PREFIX_USER_POSITIONS: &[u8] = b"userpositions";
 
 pub fn write_viewing_key<S: Storage>(store: &mut S, owner: &CanonicalAddr, key: &ViewingKey) {
     let mut user_key_store = PrefixedStorage::new(PREFIX_VIEWING_KEY, store);
@@ -23,11 +52,331 @@ pub fn read_viewing_key<S: Storage>(store: &S, owner: &CanonicalAddr) -> Option<
     user_key_store.get(owner.as_slice())
 }
 
+/// Named keys live next to the unnamed one, so that e.g. every lender or device can hold a
+/// key of its own and rotating one of them doesn't lock out the others.
+pub fn write_named_viewing_key<S: Storage>(
+    store: &mut S,
+    owner: &CanonicalAddr,
+    name: &str,
+    key: &ViewingKey,
+) -> StdResult<()> {
+    let mut names = read_viewing_key_names(store, owner)?;
+    if !names.iter().any(|existing| existing == name) {
+        names.push(name.to_string());
+        let mut name_store = PrefixedStorage::new(PREFIX_VIEWING_KEY_NAMES, store);
+        save(&mut name_store, owner.as_slice(), &names)?;
+    }
+
+    let mut key_store =
+        PrefixedStorage::multilevel(&[PREFIX_NAMED_VIEWING_KEYS, owner.as_slice()], store);
+    key_store.set(name.as_bytes(), &key.to_hashed());
+    Ok(())
+}
+
+/// Removes the named key, or the unnamed one if no name is given.
+pub fn remove_viewing_key<S: Storage>(
+    store: &mut S,
+    owner: &CanonicalAddr,
+    name: Option<&str>,
+) -> StdResult<()> {
+    let name = match name {
+        Some(name) => name,
+        None => {
+            let mut user_key_store = PrefixedStorage::new(PREFIX_VIEWING_KEY, store);
+            user_key_store.remove(owner.as_slice());
+            return Ok(());
+        }
+    };
+
+    let mut names = read_viewing_key_names(store, owner)?;
+    names.retain(|existing| existing != name);
+    let mut name_store = PrefixedStorage::new(PREFIX_VIEWING_KEY_NAMES, store);
+    if names.is_empty() {
+        name_store.remove(owner.as_slice());
+    } else {
+        save(&mut name_store, owner.as_slice(), &names)?;
+    }
+
+    let mut key_store =
+        PrefixedStorage::multilevel(&[PREFIX_NAMED_VIEWING_KEYS, owner.as_slice()], store);
+    key_store.remove(name.as_bytes());
+    Ok(())
+}
+
+pub fn read_viewing_key_names<S: ReadonlyStorage>(
+    store: &S,
+    owner: &CanonicalAddr,
+) -> StdResult<Vec<String>> {
+    let name_store = ReadonlyPrefixedStorage::new(PREFIX_VIEWING_KEY_NAMES, store);
+    Ok(may_load(&name_store, owner.as_slice())?.unwrap_or_default())
+}
+
+/// The hashes of all keys an address has set, the unnamed one first.
+pub fn read_viewing_keys<S: Storage>(store: &S, owner: &CanonicalAddr) -> StdResult<Vec<Vec<u8>>> {
+    let mut keys: Vec<Vec<u8>> = read_viewing_key(store, owner).into_iter().collect();
+
+    let key_store =
+        ReadonlyPrefixedStorage::multilevel(&[PREFIX_NAMED_VIEWING_KEYS, owner.as_slice()], store);
+    for name in read_viewing_key_names(store, owner)? {
+        if let Some(key) = key_store.get(name.as_bytes()) {
+            keys.push(key);
+        }
+    }
+
+    Ok(keys)
+}
+
+pub fn write_oracle<S: Storage>(store: &mut S, oracle: &CanonicalAddr) {
+    let mut oracle_store = PrefixedStorage::new(PREFIX_ORACLES, store);
+    oracle_store.set(oracle.as_slice(), &[1]);
+}
+
+pub fn remove_oracle<S: Storage>(store: &mut S, oracle: &CanonicalAddr) {
+    let mut oracle_store = PrefixedStorage::new(PREFIX_ORACLES, store);
+    oracle_store.remove(oracle.as_slice());
+}
+
+pub fn is_oracle<S: ReadonlyStorage>(store: &S, address: &CanonicalAddr) -> bool {
+    let oracle_store = ReadonlyPrefixedStorage::new(PREFIX_ORACLES, store);
+    oracle_store.get(address.as_slice()).is_some()
+}
+
+/// Borrowers who let lenders buy reads of their score with `PayForRead`.
+pub fn write_accepts_paid_reads<S: Storage>(store: &mut S, borrower: &CanonicalAddr, accept: bool) {
+    let mut paid_reads_store = PrefixedStorage::new(PREFIX_PAID_READS, store);
+    if accept {
+        paid_reads_store.set(borrower.as_slice(), &[1]);
+    } else {
+        paid_reads_store.remove(borrower.as_slice());
+    }
+}
+
+pub fn accepts_paid_reads<S: ReadonlyStorage>(store: &S, borrower: &CanonicalAddr) -> bool {
+    let paid_reads_store = ReadonlyPrefixedStorage::new(PREFIX_PAID_READS, store);
+    paid_reads_store.get(borrower.as_slice()).is_some()
+}
+
+/// Read access to a user's score granted to a third party, typically a lender.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Grant {
+    pub expires_at: Option<u64>,
+    pub max_reads: Option<u64>,
+    pub reads: u64,
+}
+
+impl Grant {
+    pub fn is_expired(&self, time: u64) -> bool {
+        self.expires_at.is_some_and(|expires_at| time >= expires_at)
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.max_reads
+            .is_some_and(|max_reads| self.reads >= max_reads)
+    }
+}
+
+pub fn write_grant<S: Storage>(
+    store: &mut S,
+    owner: &CanonicalAddr,
+    grantee: &CanonicalAddr,
+    grant: &Grant,
+) -> StdResult<()> {
+    let mut grantees = read_grantees(store, owner)?;
+    if !grantees.contains(grantee) {
+        grantees.push(grantee.clone());
+        let mut grantee_store = PrefixedStorage::new(PREFIX_GRANTEES, store);
+        save(&mut grantee_store, owner.as_slice(), &grantees)?;
+    }
+
+    let mut grant_store = PrefixedStorage::multilevel(&[PREFIX_GRANTS, owner.as_slice()], store);
+    save(&mut grant_store, grantee.as_slice(), grant)
+}
+
+pub fn read_grant<S: ReadonlyStorage>(
+    store: &S,
+    owner: &CanonicalAddr,
+    grantee: &CanonicalAddr,
+) -> StdResult<Option<Grant>> {
+    let grant_store =
+        ReadonlyPrefixedStorage::multilevel(&[PREFIX_GRANTS, owner.as_slice()], store);
+    may_load(&grant_store, grantee.as_slice())
+}
+
+pub fn remove_grant<S: Storage>(
+    store: &mut S,
+    owner: &CanonicalAddr,
+    grantee: &CanonicalAddr,
+) -> StdResult<()> {
+    let mut grantees = read_grantees(store, owner)?;
+    grantees.retain(|address| address != grantee);
+    let mut grantee_store = PrefixedStorage::new(PREFIX_GRANTEES, store);
+    if grantees.is_empty() {
+        grantee_store.remove(owner.as_slice());
+    } else {
+        save(&mut grantee_store, owner.as_slice(), &grantees)?;
+    }
+
+    let mut grant_store = PrefixedStorage::multilevel(&[PREFIX_GRANTS, owner.as_slice()], store);
+    grant_store.remove(grantee.as_slice());
+    Ok(())
+}
+
+/// Storage can't be iterated, so the grantees of every owner are tracked in a list of their own.
+fn read_grantees<S: ReadonlyStorage>(
+    store: &S,
+    owner: &CanonicalAddr,
+) -> StdResult<Vec<CanonicalAddr>> {
+    let grantee_store = ReadonlyPrefixedStorage::new(PREFIX_GRANTEES, store);
+    Ok(may_load(&grantee_store, owner.as_slice())?.unwrap_or_default())
+}
+
+/// All grants an owner has issued, in the order they were first granted.
+pub fn list_grants<S: ReadonlyStorage>(
+    store: &S,
+    owner: &CanonicalAddr,
+) -> StdResult<Vec<(CanonicalAddr, Grant)>> {
+    let mut grants = vec![];
+    for grantee in read_grantees(store, owner)? {
+        if let Some(grant) = read_grant(store, owner, &grantee)? {
+            grants.push((grantee, grant));
+        }
+    }
+
+    Ok(grants)
+}
+
+/// A lender's request for a single read of a borrower's score.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ScoreRequest {
+    pub lender: CanonicalAddr,
+    pub purpose: String,
+    pub expires_at: Option<u64>,
+    pub requested_at: u64,
+}
+
+impl ScoreRequest {
+    pub fn is_expired(&self, time: u64) -> bool {
+        self.expires_at.is_some_and(|expires_at| time >= expires_at)
+    }
+}
+
+/// The requests pending for a borrower, oldest first.
+pub fn read_score_requests<S: ReadonlyStorage>(
+    store: &S,
+    borrower: &CanonicalAddr,
+) -> StdResult<Vec<ScoreRequest>> {
+    let request_store = ReadonlyPrefixedStorage::new(PREFIX_SCORE_REQUESTS, store);
+    Ok(may_load(&request_store, borrower.as_slice())?.unwrap_or_default())
+}
+
+pub fn write_score_requests<S: Storage>(
+    store: &mut S,
+    borrower: &CanonicalAddr,
+    requests: &[ScoreRequest],
+) -> StdResult<()> {
+    let mut request_store = PrefixedStorage::new(PREFIX_SCORE_REQUESTS, store);
+    if requests.is_empty() {
+        request_store.remove(borrower.as_slice());
+        return Ok(());
+    }
+
+    save(&mut request_store, borrower.as_slice(), &requests)
+}
+
+/// Contracts borrowers may subscribe, each with the code hash its callbacks are sent with.
+pub fn write_callback_contract<S: Storage>(
+    store: &mut S,
+    contract: &CanonicalAddr,
+    code_hash: &str,
+) -> StdResult<()> {
+    let mut callback_store = PrefixedStorage::new(PREFIX_CALLBACK_CONTRACTS, store);
+    save(&mut callback_store, contract.as_slice(), &code_hash)
+}
+
+pub fn remove_callback_contract<S: Storage>(store: &mut S, contract: &CanonicalAddr) {
+    let mut callback_store = PrefixedStorage::new(PREFIX_CALLBACK_CONTRACTS, store);
+    callback_store.remove(contract.as_slice());
+}
+
+pub fn read_callback_contract<S: ReadonlyStorage>(
+    store: &S,
+    contract: &CanonicalAddr,
+) -> StdResult<Option<String>> {
+    let callback_store = ReadonlyPrefixedStorage::new(PREFIX_CALLBACK_CONTRACTS, store);
+    may_load(&callback_store, contract.as_slice())
+}
+
+/// The contracts notified whenever the user's score is recorded.
+pub fn read_subscribers<S: ReadonlyStorage>(
+    store: &S,
+    owner: &CanonicalAddr,
+) -> StdResult<Vec<CanonicalAddr>> {
+    let subscriber_store = ReadonlyPrefixedStorage::new(PREFIX_SUBSCRIBERS, store);
+    Ok(may_load(&subscriber_store, owner.as_slice())?.unwrap_or_default())
+}
+
+pub fn write_subscribers<S: Storage>(
+    store: &mut S,
+    owner: &CanonicalAddr,
+    subscribers: &[CanonicalAddr],
+) -> StdResult<()> {
+    let mut subscriber_store = PrefixedStorage::new(PREFIX_SUBSCRIBERS, store);
+    if subscribers.is_empty() {
+        subscriber_store.remove(owner.as_slice());
+        return Ok(());
+    }
+
+    save(&mut subscriber_store, owner.as_slice(), &subscribers)
+}
+
+/// The subscribers that haven't been sent the user's latest score yet.
+pub fn read_pending_callbacks<S: ReadonlyStorage>(
+    store: &S,
+    owner: &CanonicalAddr,
+) -> StdResult<Vec<CanonicalAddr>> {
+    let pending_store = ReadonlyPrefixedStorage::new(PREFIX_PENDING_CALLBACKS, store);
+    Ok(may_load(&pending_store, owner.as_slice())?.unwrap_or_default())
+}
+
+pub fn write_pending_callbacks<S: Storage>(
+    store: &mut S,
+    owner: &CanonicalAddr,
+    contracts: &[CanonicalAddr],
+) -> StdResult<()> {
+    let mut pending_store = PrefixedStorage::new(PREFIX_PENDING_CALLBACKS, store);
+    if contracts.is_empty() {
+        pending_store.remove(owner.as_slice());
+        return Ok(());
+    }
+
+    save(&mut pending_store, owner.as_slice(), &contracts)
+}
+
+/// Queries have no access to the current block, so every handle stores it and queries use
+/// the block of the latest transaction as their notion of "now".
+/// That block lags behind while the contract sees no transactions.
+pub fn save_block<S: Storage>(store: &mut S, block: &BlockInfo) -> StdResult<()> {
+    let mut config_store = PrefixedStorage::new(PREFIX_CONFIG, store);
+    save(&mut config_store, KEY_LAST_BLOCK, block)
+}
+
+pub fn load_block_time<S: ReadonlyStorage>(store: &S) -> StdResult<u64> {
+    let config_store = ReadonlyPrefixedStorage::new(PREFIX_CONFIG, store);
+    let block: Option<BlockInfo> = may_load(&config_store, KEY_LAST_BLOCK)?;
+    Ok(block.map_or(0, |block| block.time))
+}
+
 #[derive(Serialize, Debug, Deserialize, Clone, PartialEq, JsonSchema)]
 pub struct Constants {
     pub contract_address: HumanAddr,
 }
 
+#[derive(Serialize, Debug, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct ContractVersion {
+    pub name: String,
+    pub version: String,
+}
+
 pub struct Config<'a, S: Storage> {
     storage: PrefixedStorage<'a, S>,
 }
@@ -43,13 +392,21 @@ impl<'a, S: ReadonlyStorage> ReadonlyConfig<'a, S> {
         }
     }
 
-    fn as_readonly(&self) -> ReadonlyConfigImpl<ReadonlyPrefixedStorage<S>> {
+    fn as_readonly(&self) -> ReadonlyConfigImpl<'_, ReadonlyPrefixedStorage<'_, S>> {
         ReadonlyConfigImpl(&self.storage)
     }
 
     pub fn constants(&self) -> StdResult<Constants> {
         self.as_readonly().constants()
     }
+
+    pub fn contract_version(&self) -> StdResult<ContractVersion> {
+        self.as_readonly().contract_version()
+    }
+
+    pub fn distribution(&self) -> StdResult<Distribution> {
+        self.as_readonly().distribution()
+    }
 }
 
 /// This struct refactors out the readonly methods that we need for `Config` and `ReadonlyConfig`
@@ -68,6 +425,23 @@ impl<'a, S: ReadonlyStorage> ReadonlyConfigImpl<'a, S> {
         bincode2::deserialize::<Constants>(&consts_bytes)
             .map_err(|e| StdError::serialize_err(type_name::<Constants>(), e))
     }
+
+    fn contract_version(&self) -> StdResult<ContractVersion> {
+        let version_bytes = self
+            .0
+            .get(KEY_CONTRACT_VERSION)
+            .ok_or_else(|| StdError::generic_err("no contract version stored in configuration"))?;
+        bincode2::deserialize::<ContractVersion>(&version_bytes)
+            .map_err(|e| StdError::serialize_err(type_name::<ContractVersion>(), e))
+    }
+
+    fn distribution(&self) -> StdResult<Distribution> {
+        match self.0.get(KEY_DISTRIBUTION) {
+            Some(bytes) => bincode2::deserialize::<Distribution>(&bytes)
+                .map_err(|e| StdError::serialize_err(type_name::<Distribution>(), e)),
+            None => Ok(Distribution::default()),
+        }
+    }
 }
 
 fn ser_bin_data<T: Serialize>(obj: &T) -> StdResult<Vec<u8>> {
@@ -88,23 +462,629 @@ impl<'a, S: Storage> Config<'a, S> {
         }
     }
 
+    fn as_readonly(&self) -> ReadonlyConfigImpl<'_, PrefixedStorage<'_, S>> {
+        ReadonlyConfigImpl(&self.storage)
+    }
+
     pub fn set_constants(&mut self, constants: &Constants) -> StdResult<()> {
         set_bin_data(&mut self.storage, KEY_CONSTANTS, constants)
     }
+
+    pub fn contract_version(&self) -> StdResult<ContractVersion> {
+        self.as_readonly().contract_version()
+    }
+
+    pub fn set_contract_version(&mut self, version: &ContractVersion) -> StdResult<()> {
+        set_bin_data(&mut self.storage, KEY_CONTRACT_VERSION, version)
+    }
+
+    pub fn distribution(&self) -> StdResult<Distribution> {
+        self.as_readonly().distribution()
+    }
+
+    pub fn set_distribution(&mut self, distribution: &Distribution) -> StdResult<()> {
+        set_bin_data(&mut self.storage, KEY_DISTRIBUTION, distribution)
+    }
+}
+
+pub const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+/// Resolution of the percentile ranks, in score points
+pub const PERCENTILE_BUCKET_WIDTH: u64 = 10;
+
+/// Running aggregates over the current score of every user. They are updated whenever a
+/// score is written or deleted, so statistics never have to iterate over the users.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Distribution {
+    pub sum: u64,
+    /// Number of scores per band, in the order of `SCORE_BANDS`
+    pub bands: Vec<u64>,
+    /// Number of scores per day they were recorded on, as `(day, count)` sorted by day
+    pub days: Vec<(u64, u64)>,
+    /// Number of scores per `PERCENTILE_BUCKET_WIDTH` wide score bucket, as `(bucket, count)`
+    /// sorted by bucket
+    pub buckets: Vec<(u64, u64)>,
+}
+
+impl Distribution {
+    pub fn insert(&mut self, user: &User) {
+        self.sum = self.sum.saturating_add(user.score);
+
+        let band = band_index(user.band);
+        if self.bands.len() <= band {
+            self.bands.resize(SCORE_BANDS.len(), 0);
+        }
+        self.bands[band] += 1;
+
+        increment(&mut self.days, user.timestamp / SECONDS_PER_DAY);
+        increment(&mut self.buckets, user.score / PERCENTILE_BUCKET_WIDTH);
+    }
+
+    pub fn remove(&mut self, user: &User) {
+        self.sum = self.sum.saturating_sub(user.score);
+
+        if let Some(count) = self.bands.get_mut(band_index(user.band)) {
+            *count = count.saturating_sub(1);
+        }
+
+        decrement(&mut self.days, user.timestamp / SECONDS_PER_DAY);
+        decrement(&mut self.buckets, user.score / PERCENTILE_BUCKET_WIDTH);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().map(|(_, count)| count).sum()
+    }
+
+    /// Share of scores, in percent, below the given one. Scores in the same bucket count
+    /// half, so the rank is approximate to within a bucket.
+    pub fn percentile(&self, score: u64) -> Option<u8> {
+        let total = self.count();
+        if total == 0 {
+            return None;
+        }
+
+        let bucket = score / PERCENTILE_BUCKET_WIDTH;
+        let (below, same) = self.buckets.iter().take_while(|(b, _)| *b <= bucket).fold(
+            (0, 0),
+            |(below, same), &(b, count)| {
+                if b < bucket {
+                    (below + count, same)
+                } else {
+                    (below, same + count)
+                }
+            },
+        );
+
+        Some(((below * 2 + same) * 100 / (total * 2)) as u8)
+    }
+
+    pub fn band_count(&self, band: ScoreBand) -> u64 {
+        self.bands.get(band_index(band)).copied().unwrap_or(0)
+    }
+
+    /// Scores are only tracked by day, so a score counts as stale from the end of the day
+    /// it was recorded on plus the validity window.
+    pub fn stale_count(&self, now: u64, score_validity: u64) -> u64 {
+        self.days
+            .iter()
+            .take_while(|(day, _)| {
+                (day + 1)
+                    .saturating_mul(SECONDS_PER_DAY)
+                    .saturating_add(score_validity)
+                    <= now
+            })
+            .map(|(_, count)| count)
+            .sum()
+    }
+}
+
+/// Counts one more item under `key` in a `(key, count)` list sorted by key.
+fn increment(counts: &mut Vec<(u64, u64)>, key: u64) {
+    match counts.binary_search_by_key(&key, |&(key, _)| key) {
+        Ok(pos) => counts[pos].1 += 1,
+        Err(pos) => counts.insert(pos, (key, 1)),
+    }
+}
+
+fn decrement(counts: &mut Vec<(u64, u64)>, key: u64) {
+    if let Ok(pos) = counts.binary_search_by_key(&key, |&(key, _)| key) {
+        counts[pos].1 -= 1;
+        if counts[pos].1 == 0 {
+            counts.remove(pos);
+        }
+    }
+}
+
+fn band_index(band: ScoreBand) -> usize {
+    SCORE_BANDS.iter().position(|b| *b == band).unwrap_or(0)
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct State {
+    pub status: ContractStatus,
+    pub admin: HumanAddr,
+    pub pending_admin: Option<HumanAddr>,
+    pub max_size: u16,
+    pub score_validity: u64,
+    pub min_score: u64,
+    pub max_score: u64,
+    /// Aggregate statistics over fewer users than this are not disclosed
+    pub k_anonymity: u64,
+    pub read_fee: Option<ReadFee>,
+    pub record_fee: Option<Coin>,
+    pub prng_seed: Vec<u8>,
+}
+
+/// `State` as it was stored before versioning, without a version tag. These deployments had
+/// no admin, so the first `MigrateStorage` hands the contract to whoever proves they deployed
+/// it.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct StateV1 {
     pub max_size: u16,
     pub score_count: u64,
     pub prng_seed: Vec<u8>,
 }
 
+impl StateV1 {
+    /// Fills in the settings added since with their defaults. Scoring oracles have to be
+    /// registered by the admin before scores can be recorded again.
+    pub fn upgrade(self, admin: HumanAddr) -> State {
+        State {
+            status: ContractStatus::Normal,
+            admin,
+            pending_admin: None,
+            max_size: self.max_size,
+            score_validity: DEFAULT_SCORE_VALIDITY,
+            min_score: DEFAULT_MIN_SCORE,
+            max_score: DEFAULT_MAX_SCORE,
+            k_anonymity: DEFAULT_K_ANONYMITY,
+            read_fee: None,
+            record_fee: None,
+            prng_seed: self.prng_seed,
+        }
+    }
+}
+
+/// Every layout `State` has been stored with, like `VersionedUser`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+enum VersionedState {
+    V1(StateV1),
+    V2(Box<State>),
+}
+
+fn load_versioned_state<S: ReadonlyStorage>(storage: &S) -> StdResult<VersionedState> {
+    let bytes = storage
+        .get(CONFIG_KEY)
+        .ok_or_else(|| StdError::not_found(type_name::<State>()))?;
+
+    // Untagged states predate versioning and use the V1 layout. Their first 4 bytes are
+    // `max_size` (u16) followed by the low bytes of `score_count`, which only read as the tag
+    // of an existing variant if `max_size` is 0 or 1 and the count is a multiple of 65536.
+    Bincode2::deserialize::<VersionedState>(&bytes)
+        .or_else(|_| Bincode2::deserialize::<StateV1>(&bytes).map(VersionedState::V1))
+}
+
+pub fn load_state<S: ReadonlyStorage>(storage: &S) -> StdResult<State> {
+    match load_versioned_state(storage)? {
+        VersionedState::V1(_) => Err(StdError::generic_err(
+            "State predates versioning, it has to be claimed with MigrateStorage first",
+        )),
+        VersionedState::V2(state) => Ok(*state),
+    }
+}
+
+/// Returns the stored state if it predates versioning and hasn't been claimed yet.
+pub fn may_load_legacy_state<S: ReadonlyStorage>(storage: &S) -> StdResult<Option<StateV1>> {
+    match load_versioned_state(storage)? {
+        VersionedState::V1(state) => Ok(Some(state)),
+        VersionedState::V2(_) => Ok(None),
+    }
+}
+
+pub fn save_state<S: Storage>(storage: &mut S, state: &State) -> StdResult<()> {
+    save(
+        storage,
+        CONFIG_KEY,
+        &VersionedState::V2(Box::new(state.clone())),
+    )
+}
+
+/// The current storage version of `User` records.
+pub const USER_VERSION: u8 = 2;
+
+/// `User` as it was stored before scores carried a band and a breakdown. These records were
+/// written without a version tag.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct UserV1 {
+    pub score: u64,
+    pub timestamp: u64,
+    pub description: Vec<u8>,
+}
+
+impl From<UserV1> for User {
+    fn from(user: UserV1) -> Self {
+        let band = ScoreBand::named_in(&String::from_utf8_lossy(&user.description))
+            .unwrap_or_else(|| ScoreBand::from_score(user.score));
+        User {
+            score: user.score,
+            timestamp: user.timestamp,
+            description: user.description,
+            band,
+            components: vec![],
+            loan_ceiling: None,
+        }
+    }
+}
+
+/// Every layout `User` records have been stored with. A layout change adds a variant and
+/// bumps `USER_VERSION`, so records written by earlier versions stay readable and can be
+/// upgraded in place by `MigrateStorage`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+enum VersionedUser {
+    V1(UserV1),
+    V2(User),
+}
+
+impl VersionedUser {
+    fn version(&self) -> u8 {
+        match self {
+            VersionedUser::V1(_) => 1,
+            VersionedUser::V2(_) => 2,
+        }
+    }
+
+    fn into_latest(self) -> User {
+        match self {
+            VersionedUser::V1(user) => user.into(),
+            VersionedUser::V2(user) => user,
+        }
+    }
+}
+
+fn may_load_versioned_user<S: ReadonlyStorage>(
+    storage: &S,
+    key: &[u8],
+) -> StdResult<Option<VersionedUser>> {
+    let bytes = match storage.get(key) {
+        Some(bytes) => bytes,
+        None => return Ok(None),
+    };
+
+    // Untagged records predate versioning and use the V1 layout. Their first bytes are the
+    // score, which can only be mistaken for a version tag if it is 0 or 1.
+    Bincode2::deserialize::<VersionedUser>(&bytes)
+        .or_else(|_| Bincode2::deserialize::<UserV1>(&bytes).map(VersionedUser::V1))
+        .map(Some)
+}
+
+/// Records written before they moved under `PREFIX_USERS` are still found at the root of the
+/// storage, until they are migrated or rewritten.
+fn may_load_stored_user<S: ReadonlyStorage>(
+    storage: &S,
+    address: &CanonicalAddr,
+) -> StdResult<Option<(VersionedUser, bool)>> {
+    let user_store = ReadonlyPrefixedStorage::new(PREFIX_USERS, storage);
+    if let Some(user) = may_load_versioned_user(&user_store, address.as_slice())? {
+        return Ok(Some((user, false)));
+    }
+
+    Ok(may_load_versioned_user(storage, address.as_slice())?.map(|user| (user, true)))
+}
+
+pub fn may_load_user<S: ReadonlyStorage>(
+    storage: &S,
+    address: &CanonicalAddr,
+) -> StdResult<Option<User>> {
+    Ok(may_load_stored_user(storage, address)?.map(|(user, _)| user.into_latest()))
+}
+
+/// Like `may_load_user`, but also tells whether the record is counted in the `Distribution`.
+/// Records still at the root of the storage predate it and are counted once migrated.
+pub fn may_load_counted_user<S: ReadonlyStorage>(
+    storage: &S,
+    address: &CanonicalAddr,
+) -> StdResult<Option<(User, bool)>> {
+    Ok(may_load_stored_user(storage, address)?
+        .map(|(user, at_root)| (user.into_latest(), !at_root)))
+}
+
+pub fn save_user<S: Storage>(
+    storage: &mut S,
+    address: &CanonicalAddr,
+    user: &User,
+) -> StdResult<()> {
+    storage.remove(address.as_slice());
+    add_to_user_index(storage, address)?;
+
+    let mut user_store = PrefixedStorage::new(PREFIX_USERS, storage);
+    save(
+        &mut user_store,
+        address.as_slice(),
+        &VersionedUser::V2(user.clone()),
+    )
+}
+
+pub fn remove_user<S: Storage>(storage: &mut S, address: &CanonicalAddr) -> StdResult<()> {
+    storage.remove(address.as_slice());
+    remove_from_user_index(storage, address)?;
+
+    let mut user_store = PrefixedStorage::new(PREFIX_USERS, storage);
+    user_store.remove(address.as_slice());
+    Ok(())
+}
+
+/// Moves the record of `address` under `PREFIX_USERS` and rewrites it with the current
+/// layout. A record moved from the root is added to the `Distribution`. Returns whether the
+/// record needed either.
+pub fn migrate_user<S: Storage>(storage: &mut S, address: &CanonicalAddr) -> StdResult<bool> {
+    match may_load_stored_user(storage, address)? {
+        Some((user, at_root)) if at_root || user.version() < USER_VERSION => {
+            let user = user.into_latest();
+            save_user(storage, address, &user)?;
+            if at_root {
+                let mut config = Config::from_storage(storage);
+                let mut distribution = config.distribution()?;
+                distribution.insert(&user);
+                config.set_distribution(&distribution)?;
+            }
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// The index of every address with a score on record, in the order they were first recorded.
+/// A removed address is blanked out rather than moved, so positions stay put while the admin
+/// pages through the index. Each address's position is stored alongside.
+fn add_to_user_index<S: Storage>(store: &mut S, address: &CanonicalAddr) -> StdResult<()> {
+    let position_store = ReadonlyPrefixedStorage::new(PREFIX_USER_POSITIONS, store);
+    if position_store.get(address.as_slice()).is_some() {
+        return Ok(());
+    }
+
+    let mut index_store = PrefixedStorage::new(PREFIX_USER_INDEX, store);
+    let mut index = AppendStoreMut::attach_or_create(&mut index_store)?;
+    let position = index.len();
+    index.push(address)?;
+
+    let mut position_store = PrefixedStorage::new(PREFIX_USER_POSITIONS, store);
+    save(&mut position_store, address.as_slice(), &position)?;
+
+    let count = user_count(store)?;
+    let mut config_store = PrefixedStorage::new(PREFIX_CONFIG, store);
+    save(&mut config_store, KEY_USER_COUNT, &(count + 1))
+}
+
+fn remove_from_user_index<S: Storage>(store: &mut S, address: &CanonicalAddr) -> StdResult<()> {
+    let mut position_store = PrefixedStorage::new(PREFIX_USER_POSITIONS, store);
+    let position: u32 = match may_load(&position_store, address.as_slice())? {
+        Some(position) => position,
+        None => return Ok(()),
+    };
+    position_store.remove(address.as_slice());
+
+    let mut index_store = PrefixedStorage::new(PREFIX_USER_INDEX, store);
+    let mut index = AppendStoreMut::<CanonicalAddr, _>::attach_or_create(&mut index_store)?;
+    index.set_at(position, &CanonicalAddr::default())?;
+
+    let count = user_count(store)?;
+    let mut config_store = PrefixedStorage::new(PREFIX_CONFIG, store);
+    save(&mut config_store, KEY_USER_COUNT, &count.saturating_sub(1))
+}
+
+/// Returns the addresses at positions `start..start + limit` of the user index, skipping
+/// removed ones, along with the size of the index including them.
+pub fn get_user_index<S: ReadonlyStorage>(
+    store: &S,
+    start: u32,
+    limit: u32,
+) -> StdResult<(Vec<CanonicalAddr>, u32)> {
+    let store = ReadonlyPrefixedStorage::new(PREFIX_USER_INDEX, store);
+    let store = AppendStore::<CanonicalAddr, _, _>::attach(&store);
+    let store = if let Some(result) = store {
+        result?
+    } else {
+        return Ok((vec![], 0));
+    };
+
+    let addresses: StdResult<Vec<CanonicalAddr>> = store
+        .iter()
+        .skip(start as _)
+        .take(limit as _)
+        .filter(|address| !matches!(address, Ok(address) if address.is_empty()))
+        .collect();
+    addresses.map(|addresses| (addresses, store.len()))
+}
+
+/// The number of addresses with a score on record.
+pub fn user_count<S: ReadonlyStorage>(store: &S) -> StdResult<u64> {
+    let config_store = ReadonlyPrefixedStorage::new(PREFIX_CONFIG, store);
+    Ok(may_load(&config_store, KEY_USER_COUNT)?.unwrap_or_default())
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct User {
     pub score: u64,
     pub timestamp: u64,
     pub description: Vec<u8>,
+    pub band: ScoreBand,
+    pub components: Vec<ScoreComponent>,
+    pub loan_ceiling: Option<u64>,
+}
+
+/// Registers a public key the off-chain oracle signs score attestations with, along with
+/// the oracle address that attested scores are credited to.
+pub fn write_oracle_key<S: Storage>(
+    store: &mut S,
+    pubkey: &[u8],
+    oracle: &HumanAddr,
+) -> StdResult<()> {
+    let mut key_store = PrefixedStorage::new(PREFIX_ORACLE_KEYS, store);
+    save(&mut key_store, pubkey, oracle)
+}
+
+pub fn read_oracle_key<S: ReadonlyStorage>(
+    store: &S,
+    pubkey: &[u8],
+) -> StdResult<Option<HumanAddr>> {
+    let key_store = ReadonlyPrefixedStorage::new(PREFIX_ORACLE_KEYS, store);
+    may_load(&key_store, pubkey)
+}
+
+pub fn remove_oracle_key<S: Storage>(store: &mut S, pubkey: &[u8]) {
+    let mut key_store = PrefixedStorage::new(PREFIX_ORACLE_KEYS, store);
+    key_store.remove(pubkey);
+}
+
+/// Marks an attestation nonce of the given oracle key as used. Returns false if it already
+/// was, i.e. the attestation is being replayed.
+pub fn use_nonce<S: Storage>(store: &mut S, pubkey: &[u8], nonce: u64) -> bool {
+    let mut nonce_store = PrefixedStorage::multilevel(&[PREFIX_NONCES, pubkey], store);
+    let key = nonce.to_be_bytes();
+    if nonce_store.get(&key).is_some() {
+        return false;
+    }
+
+    nonce_store.set(&key, &[1]);
+    true
+}
+
+/// A single score recorded for a user. The full list of these is kept per user, so that
+/// the trend can be read back even though `User` only holds the latest score.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ScoreEntry {
+    pub score: u64,
+    pub timestamp: u64,
+    pub description: Vec<u8>,
+    pub oracle: HumanAddr,
+}
+
+pub fn append_score_history<S: Storage>(
+    store: &mut S,
+    owner: &CanonicalAddr,
+    entry: &ScoreEntry,
+) -> StdResult<()> {
+    let mut store = PrefixedStorage::multilevel(&[PREFIX_HISTORY, owner.as_slice()], store);
+    let mut store = AppendStoreMut::attach_or_create(&mut store)?;
+    store.push(entry)
+}
+
+/// Returns a page of the user's score history, most recent first, together with the
+/// total number of entries.
+pub fn get_score_history<S: ReadonlyStorage>(
+    store: &S,
+    owner: &CanonicalAddr,
+    page: u32,
+    page_size: u32,
+) -> StdResult<(Vec<ScoreEntry>, u64)> {
+    let store = ReadonlyPrefixedStorage::multilevel(&[PREFIX_HISTORY, owner.as_slice()], store);
+
+    // Try to access the storage of history for the account.
+    // If it doesn't exist yet, return an empty list.
+    let store = AppendStore::<ScoreEntry, _, _>::attach(&store);
+    let store = if let Some(result) = store {
+        result?
+    } else {
+        return Ok((vec![], 0));
+    };
+
+    let entries: StdResult<Vec<ScoreEntry>> = store
+        .iter()
+        .rev()
+        .skip((page as usize).saturating_mul(page_size as usize))
+        .take(page_size as _)
+        .collect();
+    entries.map(|entries| (entries, store.len() as u64))
+}
+
+/// Erases every history entry of the user. Popping off an `AppendStore` leaves the items in
+/// storage, so the entries and the length are removed one by one instead.
+pub fn clear_score_history<S: Storage>(store: &mut S, owner: &CanonicalAddr) -> StdResult<()> {
+    let mut store = PrefixedStorage::multilevel(&[PREFIX_HISTORY, owner.as_slice()], store);
+    let len = match AppendStore::<ScoreEntry, _, _>::attach(&store) {
+        Some(result) => result?.len(),
+        None => return Ok(()),
+    };
+
+    for pos in 0..len {
+        store.remove(&pos.to_be_bytes());
+    }
+    store.remove(b"len");
+    Ok(())
+}
+
+/// A read of a user's score through a grant. Every entry's hash covers the hash of the
+/// entry before it, chaining the whole log together.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AccessLogEntry {
+    pub reader: CanonicalAddr,
+    pub timestamp: u64,
+    pub fields: Vec<ScoreField>,
+    pub hash: Vec<u8>,
+}
+
+pub fn append_access_log<S: Storage>(
+    store: &mut S,
+    owner: &CanonicalAddr,
+    reader: &CanonicalAddr,
+    timestamp: u64,
+    fields: Vec<ScoreField>,
+) -> StdResult<()> {
+    let mut store = PrefixedStorage::multilevel(&[PREFIX_ACCESS_LOG, owner.as_slice()], store);
+    let mut store = AppendStoreMut::<AccessLogEntry, _>::attach_or_create(&mut store)?;
+
+    let mut content = match store.len().checked_sub(1) {
+        Some(last) => store.get_at(last)?.hash,
+        None => vec![],
+    };
+    content.extend_from_slice(reader.as_slice());
+    content.extend_from_slice(&timestamp.to_be_bytes());
+    content.extend_from_slice(&Bincode2::serialize(&fields)?);
+
+    store.push(&AccessLogEntry {
+        reader: reader.clone(),
+        timestamp,
+        fields,
+        hash: sha_256(&content).to_vec(),
+    })
+}
+
+/// Returns a page of the user's access log, most recent first, together with the total
+/// number of entries.
+pub fn get_access_log<S: ReadonlyStorage>(
+    store: &S,
+    owner: &CanonicalAddr,
+    page: u32,
+    page_size: u32,
+) -> StdResult<(Vec<AccessLogEntry>, u64)> {
+    let store = ReadonlyPrefixedStorage::multilevel(&[PREFIX_ACCESS_LOG, owner.as_slice()], store);
+    let store = AppendStore::<AccessLogEntry, _, _>::attach(&store);
+    let store = if let Some(result) = store {
+        result?
+    } else {
+        return Ok((vec![], 0));
+    };
+
+    let entries: StdResult<Vec<AccessLogEntry>> = store
+        .iter()
+        .rev()
+        .skip((page as usize).saturating_mul(page_size as usize))
+        .take(page_size as _)
+        .collect();
+    entries.map(|entries| (entries, store.len() as u64))
+}
+
+pub fn clear_access_log<S: Storage>(store: &mut S, owner: &CanonicalAddr) -> StdResult<()> {
+    let mut store = PrefixedStorage::multilevel(&[PREFIX_ACCESS_LOG, owner.as_slice()], store);
+    let len = match AppendStore::<AccessLogEntry, _, _>::attach(&store) {
+        Some(result) => result?.len(),
+        None => return Ok(()),
+    };
+
+    for pos in 0..len {
+        store.remove(&pos.to_be_bytes());
+    }
+    store.remove(b"len");
+    Ok(())
 }
 
 pub fn save<T: Serialize, S: Storage>(storage: &mut S, key: &[u8], value: &T) -> StdResult<()> {
@@ -129,10 +1109,3 @@ pub fn may_load<T: DeserializeOwned, S: ReadonlyStorage>(
         None => Ok(None),
     }
 }
-
-pub fn does_user_exist<S: ReadonlyStorage>(storage: &S, key: &[u8]) -> bool {
-    match storage.get(key) {
-        Some(_value) => true,
-        None => false,
-    }
-}
@@ -8,8 +8,7 @@ pub fn ct_slice_compare(s1: &[u8], s2: &[u8]) -> bool {
 }
 
 pub fn create_hashed_password(s1: &str) -> [u8; VIEWING_KEY_SIZE] {
-    Sha256::digest(s1.as_bytes())
-        .as_slice()
+    Sha256::digest(s1.as_bytes())[..]
         .try_into()
         .expect("Wrong password length")
 }
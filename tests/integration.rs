@@ -5,14 +5,22 @@
 //! You can easily convert unit tests to integration tests.
 //! 1. First copy them over verbatum,
 //! 2. Then change
-//!      let mut deps = mock_dependencies(20, &[]);
+//!    ```ignore
+//!    let mut deps = mock_dependencies(20, &[]);
+//!    ```
 //!    to
-//!      let mut deps = mock_instance(WASM, &[]);
+//!    ```ignore
+//!    let mut deps = mock_instance(WASM, &[]);
+//!    ```
 //! 3. If you access raw storage, where ever you see something like:
-//!      deps.storage.get(CONFIG_KEY).expect("no data stored");
+//!    ```ignore
+//!    deps.storage.get(CONFIG_KEY).expect("no data stored");
+//!    ```
 //!    replace it with:
-//!      deps.with_storage(|store| {
-//!          let data = store.get(CONFIG_KEY).expect("no data stored");
-//!          //...
-//!      });
+//!    ```ignore
+//!    deps.with_storage(|store| {
+//!        let data = store.get(CONFIG_KEY).expect("no data stored");
+//!        //...
+//!    });
+//!    ```
 //! 4. Anywhere you see query(&deps, ...) you must replace it with query(&mut deps, ...)